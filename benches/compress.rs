@@ -0,0 +1,90 @@
+//! Throughput benchmarks for the frame `Compressor`.
+
+#![allow(unstable)]
+extern crate test;
+extern crate lz4rs;
+
+use std::default::Default;
+
+use test::Bencher;
+
+use lz4rs::frame::compress::Compressor;
+use lz4rs::frame::{FramePreferences, BlockSize};
+
+/// Size of a single simulated segment file.
+const SEGMENT_SIZE: usize = 1024 * 1024;
+
+/// Size of the chunks the segment is split into, e.g. pages of a memory-mapped file.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Size of the data compressed when comparing block sizes; a few 4 MB blocks.
+const LARGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Builds a segment of mildly compressible data, similar to a log of text records.
+fn segment() -> Vec<u8> {
+    records(SEGMENT_SIZE)
+}
+
+/// Builds `size` bytes of text records.
+fn records(size: usize) -> Vec<u8> {
+    let record: &[u8] = b"2015-01-20T12:00:00Z INFO lz4rs: compressed segment record ";
+    let mut data: Vec<u8> = Vec::with_capacity(size);
+    let mut n: usize = 0;
+    while data.len() < size {
+        data.push_all(record);
+        data.push_all(n.to_string().as_bytes());
+        data.push(b'\n');
+        n += 1;
+    }
+    data.truncate(size);
+    data
+}
+
+#[bench]
+fn compress_write(b: &mut Bencher) {
+    let data: Vec<u8> = segment();
+    b.bytes = data.len() as u64;
+    b.iter(|| {
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        for chunk in data.chunks(CHUNK_SIZE) {
+            compressor.write(chunk).unwrap();
+        }
+        compressor.done()
+    });
+}
+
+#[bench]
+fn compress_slices(b: &mut Bencher) {
+    let data: Vec<u8> = segment();
+    let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+    b.bytes = data.len() as u64;
+    b.iter(|| {
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        compressor.compress_slices(chunks.as_slice()).unwrap();
+        compressor.done()
+    });
+}
+
+/// Compresses `LARGE_SIZE` bytes in a single `write` with the given block size.
+fn compress_blocks(b: &mut Bencher, block_size: BlockSize) {
+    let data: Vec<u8> = records(LARGE_SIZE);
+    b.bytes = data.len() as u64;
+    b.iter(|| {
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.block_size_id = block_size;
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), Some(prefs),
+                                                                  None, None).ok().unwrap();
+        compressor.write(data.as_slice()).unwrap();
+        compressor.done()
+    });
+}
+
+#[bench]
+fn compress_64kb_blocks(b: &mut Bencher) {
+    compress_blocks(b, BlockSize::Max64KB);
+}
+
+#[bench]
+fn compress_4mb_blocks(b: &mut Bencher) {
+    compress_blocks(b, BlockSize::Max4MB);
+}
@@ -0,0 +1,76 @@
+//! Throughput benchmarks for the frame `Decompressor`.
+
+#![allow(unstable)]
+extern crate test;
+extern crate lz4rs;
+
+use std::default::Default;
+use std::iter::repeat;
+use std::io::MemReader;
+use std::io::IoErrorKind::EndOfFile;
+
+use test::Bencher;
+
+use lz4rs::frame::compress::Compressor;
+use lz4rs::frame::decompress::Decompressor;
+use lz4rs::frame::{FramePreferences, BlockSize};
+
+/// Size of the data decompressed in each benchmark; a few 4 MB blocks.
+const DATA_SIZE: usize = 16 * 1024 * 1024;
+
+/// Size of the buffer the decompressed data is read into.
+const READ_SIZE: usize = 64 * 1024;
+
+/// Compresses `DATA_SIZE` bytes of text records into a frame with the given block size.
+fn frame(block_size: BlockSize) -> Vec<u8> {
+    let record: &[u8] = b"2015-01-20T12:00:00Z INFO lz4rs: decompressed segment record ";
+    let mut data: Vec<u8> = Vec::with_capacity(DATA_SIZE);
+    let mut n: usize = 0;
+    while data.len() < DATA_SIZE {
+        data.push_all(record);
+        data.push_all(n.to_string().as_bytes());
+        data.push(b'\n');
+        n += 1;
+    }
+    data.truncate(DATA_SIZE);
+
+    let mut prefs: FramePreferences = Default::default();
+    prefs.frame_info.block_size_id = block_size;
+    let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), Some(prefs),
+                                                              None, None).ok().unwrap();
+    compressor.write(data.as_slice()).unwrap();
+    let (v, result) = compressor.done();
+    result.ok().unwrap();
+    v
+}
+
+/// Decompresses a whole frame with the given block size through a `Decompressor`.
+fn decompress_blocks(b: &mut Bencher, block_size: BlockSize) {
+    let compressed: Vec<u8> = frame(block_size);
+    let mut buf: Vec<u8> = repeat(0u8).take(READ_SIZE).collect();
+    b.bytes = DATA_SIZE as u64;
+    b.iter(|| {
+        let readr: MemReader = MemReader::new(compressed.clone());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let mut total: usize = 0;
+        loop {
+            match decompressor.read(buf.as_mut_slice()) {
+                Ok(n) => { total += n; },
+                Err(ref e) if e.kind == EndOfFile => { break; },
+                Err(e) => { panic!("{:?}", e); },
+            }
+        }
+        total
+    });
+}
+
+#[bench]
+fn decompress_64kb_blocks(b: &mut Bencher) {
+    decompress_blocks(b, BlockSize::Max64KB);
+}
+
+#[bench]
+fn decompress_4mb_blocks(b: &mut Bencher) {
+    decompress_blocks(b, BlockSize::Max4MB);
+}
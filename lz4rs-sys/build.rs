@@ -0,0 +1,132 @@
+extern crate gcc;
+extern crate "pkg-config" as pkg_config;
+
+use std::default::Default;
+use std::os;
+use std::io::File;
+use std::io::fs::PathExtensions;
+use std::io::process::{Command, ProcessOutput};
+
+/// Oldest liblz4 the `system` feature accepts: the first release whose `lz4frame.h` names every
+/// field of the structs in `lz4frame::types`, down to `favorDecSpeed`. Older releases keep
+/// reserved fields there and are better served by the bundled sources.
+const MIN_SYSTEM_VERSION: &'static str = "1.8.2";
+
+/// The lz4 release the `ext/lz4` submodule is pinned at. The bundled sources are only built if
+/// the checkout's `lz4.h` reports this version, since the bindings follow its `lz4frame.h`.
+const BUNDLED_VERSION: &'static str = "1.10.0";
+
+/// The `cfg`s set for an lz4 of at least the given version, which declare the functions of
+/// `lz4frame.h` that only exist since then: `LZ4F_headerSize()` since 1.9.0, and the dictionary
+/// functions, which only became part of the shared library's API in 1.10.0. The bundled sources
+/// get all of them.
+const VERSION_CFGS: [(&'static str, &'static str); 2] = [
+    ("1.9.0", "lz4_v1_9"),
+    ("1.10.0", "lz4_v1_10"),
+];
+
+/// Runs pkg-config on liblz4 with `args`, returning its output if it succeeds.
+fn pkg_config_output(args: &[&str]) -> Option<String> {
+    let output: ProcessOutput = match Command::new("pkg-config").args(args).arg("liblz4").output() {
+        Ok(output) => output,
+        Err(_) => { return None; },
+    };
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.output).ok()
+}
+
+/// Reads the version the `lz4.h` at `path` declares, as "major.minor.release".
+fn header_version(path: &Path) -> Option<String> {
+    let header: String = match File::open(path).read_to_string() {
+        Ok(header) => header,
+        Err(_) => { return None; },
+    };
+    let mut parts: Vec<String> = Vec::new();
+    for name in ["LZ4_VERSION_MAJOR", "LZ4_VERSION_MINOR", "LZ4_VERSION_RELEASE"].iter() {
+        let value: Option<String> = header.as_slice().lines().filter_map(|line| {
+            let words: Vec<&str> = line.words().collect();
+            if words.len() >= 3 && words[0] == "#define" && words[1] == *name {
+                Some(words[2].to_string())
+            } else {
+                None
+            }
+        }).next();
+        match value {
+            Some(value) => { parts.push(value); },
+            None => { return None; },
+        }
+    }
+    Some(parts.connect("."))
+}
+
+fn main() {
+    // types-only builds don't need the C library
+    if os::getenv("CARGO_FEATURE_TYPES_ONLY").is_some() {
+        return;
+    }
+
+    if os::getenv("CARGO_FEATURE_SYSTEM").is_some() {
+        let opts: pkg_config::Options = pkg_config::Options {
+            statik: false,
+            atleast_version: Some(MIN_SYSTEM_VERSION.to_string()),
+        };
+        // without a recent enough liblz4, fall back to the bundled sources
+        match pkg_config::find_library_opts("liblz4", &opts) {
+            Ok(()) => {
+                for &(version, cfg) in VERSION_CFGS.iter() {
+                    let atleast: String = format!("--atleast-version={}", version);
+                    if pkg_config_output(&[atleast.as_slice()]).is_some() {
+                        println!("cargo:rustc-cfg={}", cfg);
+                    }
+                }
+
+                // only the layout probe is left to build, against the system's lz4frame.h
+                let cflags: String = pkg_config_output(&["--cflags-only-I"])
+                                         .unwrap_or(String::new());
+                let config: gcc::Config = gcc::Config {
+                    include_directories: cflags.as_slice().words()
+                                               .filter(|flag| flag.starts_with("-I"))
+                                               .map(|flag| Path::new(flag.slice_from(2)))
+                                               .collect(),
+                    ..Default::default()
+                };
+                gcc::compile_library("liblz4rs_layout.a", &config, &["src/layout.c"]);
+                return;
+            },
+            Err(err) => {
+                println!("cargo:warning=the `system` feature found no liblz4 {} or later ({}), \
+                          building the bundled sources instead", MIN_SYSTEM_VERSION, err);
+            },
+        }
+    }
+
+    if !Path::new("ext/lz4/lib/lz4.c").exists() {
+        panic!("lz4rs-sys/ext/lz4 is empty: check it out with `git submodule update --init`, or \
+                enable the `system` feature with liblz4 {} or later installed",
+               MIN_SYSTEM_VERSION);
+    }
+    match header_version(&Path::new("ext/lz4/lib/lz4.h")) {
+        Some(ref version) if version.as_slice() == BUNDLED_VERSION => {},
+        version => {
+            panic!("lz4rs-sys/ext/lz4 holds lz4 {} instead of the pinned {}: check it out with \
+                    `git submodule update --init`",
+                   version.unwrap_or("of an unknown version".to_string()), BUNDLED_VERSION);
+        },
+    }
+    for &(_, cfg) in VERSION_CFGS.iter() {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+
+    let config: gcc::Config = gcc::Config {
+        include_directories: vec![Path::new("ext/lz4/lib")],
+        ..Default::default()
+    };
+    gcc::compile_library("liblz4.a", &config, &[
+        "ext/lz4/lib/lz4.c",
+        "ext/lz4/lib/lz4frame.c",
+        "ext/lz4/lib/lz4hc.c",
+        "ext/lz4/lib/xxhash.c",
+        "src/layout.c",]);
+}
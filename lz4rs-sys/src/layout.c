@@ -0,0 +1,41 @@
+/* Reports the sizes and field offsets of the lz4frame structs as the C compiler lays them out,
+ * so that the layout tests can compare them with the #[repr(C)] structs on the Rust side.
+ * lz4frame.h is found in ext/lz4/lib, or where the system keeps it with the `system` feature. */
+
+#include <stddef.h>
+#include "lz4.h"
+#include "lz4frame.h"
+
+/* skipChecksums only arrived in lz4 1.9.4; a system liblz4 can be as old as 1.8.2. */
+#if LZ4_VERSION_NUMBER >= 10904
+#define LZ4RS_SKIP_CHECKSUMS skipChecksums
+#else
+#define LZ4RS_SKIP_CHECKSUMS reserved[0]
+#endif
+
+size_t lz4rs_sys_sizeof_frame_info(void) { return sizeof(LZ4F_frameInfo_t); }
+size_t lz4rs_sys_sizeof_preferences(void) { return sizeof(LZ4F_preferences_t); }
+size_t lz4rs_sys_sizeof_compress_options(void) { return sizeof(LZ4F_compressOptions_t); }
+size_t lz4rs_sys_sizeof_decompress_options(void) { return sizeof(LZ4F_decompressOptions_t); }
+
+size_t lz4rs_sys_offsetof_content_size(void) {
+    return offsetof(LZ4F_frameInfo_t, contentSize);
+}
+size_t lz4rs_sys_offsetof_compression_level(void) {
+    return offsetof(LZ4F_preferences_t, compressionLevel);
+}
+size_t lz4rs_sys_offsetof_auto_flush(void) {
+    return offsetof(LZ4F_preferences_t, autoFlush);
+}
+size_t lz4rs_sys_offsetof_dict_id(void) {
+    return offsetof(LZ4F_frameInfo_t, dictID);
+}
+size_t lz4rs_sys_offsetof_block_checksum_flag(void) {
+    return offsetof(LZ4F_frameInfo_t, blockChecksumFlag);
+}
+size_t lz4rs_sys_offsetof_favor_dec_speed(void) {
+    return offsetof(LZ4F_preferences_t, favorDecSpeed);
+}
+size_t lz4rs_sys_offsetof_skip_checksums(void) {
+    return offsetof(LZ4F_decompressOptions_t, LZ4RS_SKIP_CHECKSUMS);
+}
@@ -0,0 +1,37 @@
+//! Raw bindings to liblz4: everything declared in `lz4.h`, `lz4hc.h`, `lz4frame.h` and
+//! `xxhash.h`, re-exported at the crate root under their C names. The build script compiles the
+//! bundled sources in `ext/lz4` and links them statically. With the `system` feature it links the
+//! system's liblz4 instead, if pkg-config finds version 1.8.2 or later. The `lz4frame` functions
+//! the linked library doesn't have yet are left out, see its declarations.
+//!
+//! The crate is `#![no_std]` and only needs `core` and `libc`. With the `types-only` feature the C
+//! library isn't built, which leaves the types and constants usable by crates that implement the
+//! formats themselves; calling any of the functions then fails to link.
+
+#![allow(unstable)]
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(dead_code)]
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(not(test))]
+extern crate core;
+
+extern crate libc;
+
+pub use lz4::*;
+pub use lz4hc::*;
+pub use lz4frame::*;
+pub use xxhash::*;
+
+pub mod lz4;
+pub mod lz4hc;
+pub mod lz4frame;
+pub mod xxhash;
+
+/// Outside of tests, stands in for the parts of `std` the bindings use, so that they (and
+/// `#[derive]`) can refer to them the same way either way.
+#[cfg(not(test))]
+mod std {
+    pub use core::{clone, cmp, default, fmt, marker, mem, option, ptr};
+}
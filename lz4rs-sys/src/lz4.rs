@@ -0,0 +1,346 @@
+//! Rust bindings to the functions in `lz4.h`
+
+#![allow(unstable)]
+#![allow(non_snake_case)]
+#![allow(unused_imports)]
+
+extern crate libc;
+
+use libc::{c_uint, c_int, size_t, c_char, c_void, c_ulonglong};
+
+/// Largest input `LZ4_compress_default()` accepts.
+pub const LZ4_MAX_INPUT_SIZE: c_int = 0x7E000000;
+
+/// `LZ4_MEMORY_USAGE` lz4.c is compiled with: the hash table takes 2^14 bytes.
+pub const LZ4_MEMORY_USAGE: usize = 14;
+
+pub const LZ4_STREAMSIZE_U64: usize = (1 << (LZ4_MEMORY_USAGE - 3)) + 4;
+pub const LZ4_STREAMSIZE: usize = LZ4_STREAMSIZE_U64 * 8;
+
+pub const LZ4_STREAMDECODESIZE_U64: usize = 4;
+pub const LZ4_STREAMDECODESIZE: usize = LZ4_STREAMDECODESIZE_U64 * 8;
+
+/// State of a compression stream. It can be allocated statically, but has to be initialized with
+/// `LZ4_resetStream()` before its first use.
+#[repr(C)]
+pub struct LZ4_stream_t {
+    pub table: [c_ulonglong; LZ4_STREAMSIZE_U64],
+}
+
+/// State of a decompression stream, set with `LZ4_setStreamDecode()`.
+#[repr(C)]
+pub struct LZ4_streamDecode_t {
+    pub table: [c_ulonglong; LZ4_STREAMDECODESIZE_U64],
+}
+
+extern {
+    // int LZ4_versionNumber(void)
+    pub fn LZ4_versionNumber() -> c_int;
+
+    // int LZ4_compress_default(const char* source, char* dest, int sourceSize, int maxDestSize);
+    /* LZ4_compress_default()
+     * Compresses 'sourceSize' bytes from buffer 'source' into already allocated 'dest' buffer of
+     * size 'maxDestSize'. Compression is guaranteed to succeed if
+     * 'maxDestSize' >= LZ4_compressBound(sourceSize).
+     * return : the number of bytes written into buffer 'dest' (necessarily <= maxDestSize)
+     *          or 0 if compression fails
+     */
+    pub fn LZ4_compress_default(source: *const c_char,
+                                dest: *mut c_char,
+                                sourceSize: c_int,
+                                maxDestSize: c_int) -> c_int;
+
+    // int LZ4_compressBound(int inputSize);
+    /* LZ4_compressBound()
+     * Provides the maximum size that LZ4 compression may output in a "worst case" scenario
+     * (input data not compressible).
+     * return : maximum output size in a "worst case" scenario
+     *          or 0, if input size is too large ( > LZ4_MAX_INPUT_SIZE)
+     */
+    pub fn LZ4_compressBound(inputSize: c_int) -> c_int;
+
+    // int LZ4_compress_fast (const char* source, char* dest, int sourceSize, int maxDestSize,
+    //                        int acceleration);
+    /* LZ4_compress_fast()
+     * Same as LZ4_compress_limitedOutput(), but allows to select an "acceleration" factor.
+     * The larger the acceleration value, the faster the algorithm, but also the lesser the
+     * compression.
+     * It's a trade-off. It can be fine tuned, with each successive value providing an additional
+     * +2/3% to speed.
+     * An acceleration value of "0" means "use Default value", which is typically 17 (see lz4.c).
+     * An acceleration value of "1" is the same as regular LZ4_compress_default()
+     * return : the number of bytes written into buffer dest (necessarily <= maxDestSize)
+     *          or 0 if compression fails
+     */
+    pub fn LZ4_compress_fast(source: *const c_char,
+                             dest: *mut c_char,
+                             sourceSize: c_int,
+                             maxDestSize: c_int,
+                             acceleration: c_int) -> c_int;
+
+    // int LZ4_sizeofState(void);
+    // int LZ4_compress_fast_extState (void* state, const char* source, char* dest,
+    //                                 int inputSize, int maxDestSize, int acceleration);
+    /* LZ4_compress_fast_extState()
+     * Same compression function, just using an externally allocated memory space to store
+     * compression state. Use LZ4_sizeofState() to know how much memory must be allocated, and
+     * allocate it on 8-bytes boundaries (using malloc() typically). Then, provide it as
+     * 'void* state' to compression function.
+     */
+    pub fn LZ4_sizeofState() -> c_int;
+    pub fn LZ4_compress_fast_extState(state: *mut c_void,
+                                      source: *const c_char,
+                                      dest: *mut c_char,
+                                      inputSize: c_int,
+                                      maxDestSize: c_int,
+                                      acceleration: c_int) -> c_int;
+
+    // int LZ4_compress_destSize (const char* source, char* dest, int* sourceSizePtr,
+    //                            int targetDestSize);
+    /* LZ4_compress_destSize()
+     * Reverse the logic, by compressing as much data as possible from 'source' buffer
+     * into already allocated buffer 'dest' of size 'targetDestSize'.
+     * This function either compresses the entire 'source' content into 'dest' if it's large
+     * enough, or fill 'dest' buffer completely with as much data as possible from 'source'.
+     *     *sourceSizePtr : will be modified to indicate how many bytes where read from 'source' to
+     *                      fill 'dest'.
+     *                      New value is necessarily <= old value.
+     *     return : Nb bytes written into 'dest' (necessarily <= targetDestSize)
+     *              or 0 if compression fails
+     */
+    pub fn LZ4_compress_destSize(source: *const c_char,
+                                 dest: *mut c_char,
+                                 sourceSizePtr: *mut c_int,
+                                 targetDestSize: c_int) -> c_int;
+
+    // int LZ4_decompress_fast (const char* source, char* dest, int originalSize);
+    /* LZ4_decompress_fast()
+     * originalSize : is the original and therefore uncompressed size
+     * return : the number of bytes read from the source buffer (in other words, the compressed
+     *          size). If the source stream is detected malformed, the function will stop decoding
+     *          and return a negative result. Destination buffer must be already allocated. Its
+     *          size must be a minimum of 'originalSize' bytes.
+     * note : This function fully respect memory boundaries for properly formed compressed data.
+     *        It is a bit faster than LZ4_decompress_safe(). However, it does not provide any
+     *        protection against intentionally modified data stream (malicious input). Use this
+     *        function in trusted environment only (data to decode comes from a trusted source).
+     */
+    pub fn LZ4_decompress_fast(source: *const c_char,
+                               dest: *mut c_char,
+                               originalSize: c_int) -> c_int;
+
+    // int LZ4_decompress_safe (const char* source, char* dest, int compressedSize,
+    //                          int maxDecompressedSize);
+    /* LZ4_decompress_safe()
+     * compressedSize : is obviously the source size
+     * maxDecompressedSize : is the size of the destination buffer, which must be already allocated.
+     * return : the number of bytes decompressed into the destination buffer (necessarily <=
+     *          maxDecompressedSize)
+     *          If the destination buffer is not large enough, decoding will stop and output an
+     *          error code (<0).
+     *          If the source stream is detected malformed, the function will stop decoding and
+     *          return a negative result.
+     *          This function is protected against buffer overflow exploits, including malicious
+     *          data packets. It never writes outside output buffer, nor reads outside input
+     *          buffer.
+     */
+    pub fn LZ4_decompress_safe(source: *const c_char,
+                               dest: *mut c_char,
+                               compressedSize: c_int,
+                               maxDecompressedSize: c_int) -> c_int;
+
+    // int LZ4_decompress_safe_partial (const char* source, char* dest, int compressedSize,
+    //                                  int targetOutputSize, int maxDecompressedSize);
+    /* LZ4_decompress_safe_partial()
+     * This function decompress a compressed block of size 'compressedSize' at position 'source'
+     * into destination buffer 'dest' of size 'maxDecompressedSize'.
+     * The function tries to stop decompressing operation as soon as 'targetOutputSize' has been
+     * reached, reducing decompression time.
+     * return : the number of bytes decoded in the destination buffer (necessarily <=
+     *          maxDecompressedSize)
+     *    Note : this number can be < 'targetOutputSize' should the compressed block to decode be
+     *           smaller.
+     *           Always control how many bytes were decoded.
+     *           If the source stream is detected malformed, the function will stop decoding and
+     *           return a negative result.
+     *           This function never writes outside of output buffer, and never reads outside of
+     *           input buffer. It is therefore protected against malicious data packets
+     */
+    pub fn LZ4_decompress_safe_partial(source: *const c_char,
+                                       dest: *mut c_char,
+                                       compressedSize: c_int,
+                                       targetOutputSize: c_int,
+                                       maxDecompressedSize: c_int) -> c_int;
+
+    // int LZ4_decompress_safe_usingDict (const char* source, char* dest, int compressedSize,
+    //                                    int maxDecompressedSize, const char* dictStart,
+    //                                    int dictSize);
+    /* LZ4_decompress_safe_usingDict()
+     * These decoding functions work the same as LZ4_decompress_safe(), but use a dictionary to
+     * decode the compressed block. They are stand-alone and don't use nor update an
+     * LZ4_streamDecode_t structure. If the dictionary ends right where dest starts, the data in
+     * front of dest is used as prefix.
+     */
+    pub fn LZ4_decompress_safe_usingDict(source: *const c_char,
+                                         dest: *mut c_char,
+                                         compressedSize: c_int,
+                                         maxDecompressedSize: c_int,
+                                         dictStart: *const c_char,
+                                         dictSize: c_int) -> c_int;
+
+    // int LZ4_decompress_fast_usingDict (const char* source, char* dest, int originalSize,
+    //                                    const char* dictStart, int dictSize);
+    pub fn LZ4_decompress_fast_usingDict(source: *const c_char,
+                                         dest: *mut c_char,
+                                         originalSize: c_int,
+                                         dictStart: *const c_char,
+                                         dictSize: c_int) -> c_int;
+
+
+    /***********************************************
+     * Streaming Compression Functions
+     * *********************************************/
+
+    // LZ4_stream_t* LZ4_createStream(void);
+    // int           LZ4_freeStream (LZ4_stream_t* streamPtr);
+    /* LZ4_createStream() will allocate and initialize an `LZ4_stream_t` structure.
+     * LZ4_freeStream() releases its memory.
+     */
+    pub fn LZ4_createStream() -> *mut LZ4_stream_t;
+    pub fn LZ4_freeStream(streamPtr: *mut LZ4_stream_t) -> c_int;
+
+    // void LZ4_resetStream (LZ4_stream_t* streamPtr);
+    /* LZ4_resetStream()
+     * An LZ4_stream_t structure can be allocated once and re-used multiple times.
+     * Use this function to init an allocated `LZ4_stream_t` structure and start a new
+     * compression.
+     */
+    pub fn LZ4_resetStream(streamPtr: *mut LZ4_stream_t);
+
+    // int LZ4_loadDict (LZ4_stream_t* streamPtr, const char* dictionary, int dictSize);
+    /* LZ4_loadDict()
+     * Use this function to load a static dictionary into LZ4_stream.
+     * Any previous data will be forgotten, only 'dictionary' will remain in memory.
+     * Loading a size of 0 is allowed.
+     * Return : dictionary size, in bytes (necessarily <= 64 KB)
+     */
+    pub fn LZ4_loadDict(streamPtr: *mut LZ4_stream_t,
+                        dictionary: *const c_char,
+                        dictSize: c_int) -> c_int;
+
+    // int LZ4_compress_fast_continue (LZ4_stream_t* streamPtr, const char* src, char* dst,
+    //                                 int srcSize, int maxDstSize, int acceleration);
+    /* LZ4_compress_fast_continue()
+     * Compress buffer content 'src', using data from previously compressed blocks as dictionary
+     * to improve compression ratio.
+     * Important : Previous data blocks are assumed to still be present and unmodified !
+     * 'dst' buffer must be already allocated.
+     * If maxDstSize >= LZ4_compressBound(srcSize), compression is guaranteed to succeed.
+     * If not, and if compressed data cannot fit into 'dst' buffer size, compression stops, and
+     * function returns a zero.
+     */
+    pub fn LZ4_compress_fast_continue(streamPtr: *mut LZ4_stream_t,
+                                      src: *const c_char,
+                                      dst: *mut c_char,
+                                      srcSize: c_int,
+                                      maxDstSize: c_int,
+                                      acceleration: c_int) -> c_int;
+
+    // int LZ4_saveDict (LZ4_stream_t* streamPtr, char* safeBuffer, int dictSize);
+    /* LZ4_saveDict()
+     * If previously compressed data block is not guaranteed to remain available at its memory
+     * location, save it into a safer place (char* safeBuffer).
+     * Note : you don't need to call LZ4_loadDict() afterwards, dictionary is immediately usable,
+     * you can therefore call LZ4_compress_fast_continue().
+     * Return : saved dictionary size in bytes (necessarily <= dictSize), or 0 if error
+     */
+    pub fn LZ4_saveDict(streamPtr: *mut LZ4_stream_t,
+                        safeBuffer: *mut c_char,
+                        dictSize: c_int) -> c_int;
+
+
+    /************************************************
+     * Streaming Decompression Functions
+     * **********************************************/
+
+    // LZ4_streamDecode_t* LZ4_createStreamDecode(void);
+    // int                 LZ4_freeStreamDecode (LZ4_streamDecode_t* LZ4_stream);
+    /* creation / destruction of streaming decompression tracking structure */
+    pub fn LZ4_createStreamDecode() -> *mut LZ4_streamDecode_t;
+    pub fn LZ4_freeStreamDecode(LZ4_stream: *mut LZ4_streamDecode_t) -> c_int;
+
+    // int LZ4_setStreamDecode (LZ4_streamDecode_t* LZ4_streamDecode, const char* dictionary,
+    //                          int dictSize);
+    /* LZ4_setStreamDecode()
+     * Use this function to instruct where to find the dictionary.
+     * Setting a size of 0 is allowed (same effect as reset).
+     * Return : 1 if OK, 0 if error
+     */
+    pub fn LZ4_setStreamDecode(LZ4_streamDecode: *mut LZ4_streamDecode_t,
+                               dictionary: *const c_char,
+                               dictSize: c_int) -> c_int;
+
+    // int LZ4_decompress_safe_continue (LZ4_streamDecode_t* LZ4_streamDecode, const char* source,
+    //                                   char* dest, int compressedSize, int maxDecompressedSize);
+    // int LZ4_decompress_fast_continue (LZ4_streamDecode_t* LZ4_streamDecode, const char* source,
+    //                                   char* dest, int originalSize);
+    /* Variations of LZ4_decompress_safe() and LZ4_decompress_fast() which use the previously
+     * decoded blocks as dictionary. Previously decoded blocks *must* remain available at the
+     * memory position where they were decoded (up to 64 KB).
+     */
+    pub fn LZ4_decompress_safe_continue(LZ4_streamDecode: *mut LZ4_streamDecode_t,
+                                        source: *const c_char,
+                                        dest: *mut c_char,
+                                        compressedSize: c_int,
+                                        maxDecompressedSize: c_int) -> c_int;
+    pub fn LZ4_decompress_fast_continue(LZ4_streamDecode: *mut LZ4_streamDecode_t,
+                                        source: *const c_char,
+                                        dest: *mut c_char,
+                                        originalSize: c_int) -> c_int;
+
+
+    /**************************************
+     * Obsolete Functions
+     * ************************************/
+
+    /* These functions are still part of lz4.h, but are superseded by the ones above. */
+    pub fn LZ4_compress(source: *const c_char, dest: *mut c_char, sourceSize: c_int) -> c_int;
+    pub fn LZ4_compress_limitedOutput(source: *const c_char,
+                                      dest: *mut c_char,
+                                      sourceSize: c_int,
+                                      maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_compress_withState(state: *mut c_void,
+                                  source: *const c_char,
+                                  dest: *mut c_char,
+                                  inputSize: c_int) -> c_int;
+    pub fn LZ4_compress_limitedOutput_withState(state: *mut c_void,
+                                                source: *const c_char,
+                                                dest: *mut c_char,
+                                                inputSize: c_int,
+                                                maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_compress_continue(LZ4_streamPtr: *mut LZ4_stream_t,
+                                 source: *const c_char,
+                                 dest: *mut c_char,
+                                 inputSize: c_int) -> c_int;
+    pub fn LZ4_compress_limitedOutput_continue(LZ4_streamPtr: *mut LZ4_stream_t,
+                                               source: *const c_char,
+                                               dest: *mut c_char,
+                                               inputSize: c_int,
+                                               maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_uncompress(source: *const c_char, dest: *mut c_char, outputSize: c_int) -> c_int;
+    pub fn LZ4_uncompress_unknownOutputSize(source: *const c_char,
+                                            dest: *mut c_char,
+                                            isize: c_int,
+                                            maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_create(inputBuffer: *mut c_char) -> *mut c_void;
+    pub fn LZ4_sizeofStreamState() -> c_int;
+    pub fn LZ4_resetStreamState(state: *mut c_void, inputBuffer: *mut c_char) -> c_int;
+    pub fn LZ4_slideInputBuffer(state: *mut c_void) -> *mut c_char;
+    pub fn LZ4_decompress_safe_withPrefix64k(source: *const c_char,
+                                             dest: *mut c_char,
+                                             compressedSize: c_int,
+                                             maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_decompress_fast_withPrefix64k(source: *const c_char,
+                                             dest: *mut c_char,
+                                             originalSize: c_int) -> c_int;
+}
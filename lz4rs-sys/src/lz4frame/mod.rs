@@ -8,16 +8,19 @@ extern crate libc;
 
 use libc::{c_uint, c_int, size_t, c_char, c_void};
 
-use std::ffi::CString;
-
 pub use self::types::*;
 
 pub mod types;
 
 // unfortunately this constant is defined as a macro so we can't import and and will need to keep
-// it updated manually
+// it updated manually. It is still 100 in current lz4frame.h: the fields that were added since r12x
+// took the place of reserved ones, so the structs kept their size and contexts their version.
+// `LZ4F_getVersion()` tells the library versions apart.
 pub const LZ4F_VERSION: c_uint = 100;
 
+/// Size of the largest frame header, which holds both the content size and a dictionary ID.
+pub const LZ4F_HEADER_SIZE_MAX: usize = 19;
+
 
 extern {
 
@@ -32,6 +35,14 @@ extern {
     /* return error code string; useful for debugging */
     pub fn LZ4F_getErrorName(code: size_t) -> *const c_char;
 
+    // unsigned LZ4F_getVersion(void);
+    /* returns LZ4F_VERSION, the version of the frame API; available since lz4 1.7.0 */
+    pub fn LZ4F_getVersion() -> c_uint;
+
+    // int LZ4F_compressionLevel_max(void);
+    /* highest compression level the library supports; available since lz4 1.8.0 */
+    pub fn LZ4F_compressionLevel_max() -> c_int;
+
 
     /***********************************
      * Simple compression function
@@ -91,7 +102,7 @@ extern {
     /* LZ4F_compressBegin() :
      * will write the frame header into dstBuffer.
      * dstBuffer must be large enough to accommodate a header (dstMaxSize). Maximum header size
-     * is 15 bytes.
+     * is LZ4F_HEADER_SIZE_MAX bytes.
      * The LZ4F_preferences_t structure is optional : you can provide NULL as argument, all
      * preferences will then be set to default.
      * The result of the function is the number of bytes written into dstBuffer for the header
@@ -198,6 +209,16 @@ extern {
 
     /* Decompression */
 
+    // size_t LZ4F_headerSize(const void* src, size_t srcSize);
+    /* LZ4F_headerSize() : v1.9.0+
+     * Provides the size of the frame header starting at src, which has to hold at least
+     * LZ4F_MIN_SIZE_TO_KNOW_HEADER_LENGTH (5) bytes.
+     * The result is between 7 and LZ4F_HEADER_SIZE_MAX, or an error code (can be tested using
+     * LZ4F_isError()).
+     */
+    #[cfg(lz4_v1_9)]
+    pub fn LZ4F_headerSize(src: *const c_void, srcSize: size_t) -> size_t;
+
     // size_t LZ4F_getFrameInfo(LZ4F_decompressionContext_t dctx,
     //                          LZ4F_frameInfo_t* frameInfoPtr,
     //                          const void* srcBuffer, size_t* srcSizePtr);
@@ -207,7 +228,7 @@ extern {
      * The objective is to extract header information without starting decompression, typically for
      * allocation purposes.
      * The function will work only if srcBuffer points at the beginning of the frame,
-     * and *srcSizePtr is large enough to decode the whole header (typically, between 7 & 15 bytes).
+     * and *srcSizePtr is large enough to decode the whole header (typically, between 7 & 19 bytes).
      * The result is copied into an LZ4F_frameInfo_t structure, which is pointed by frameInfoPtr,
      * and must be already allocated.
      * LZ4F_getFrameInfo() can also be used *after* starting decompression, on a valid
@@ -268,11 +289,98 @@ extern {
                            srcBuffer: *const c_void,
                            srcSizePtr: *mut size_t,
                            dOptPtr: *const FrameDecompressOptions) -> size_t;
-}
-
-
-
 
+    // void LZ4F_resetDecompressionContext(LZ4F_dctx* dctx);
+    /* LZ4F_resetDecompressionContext() : v1.8.0+
+     * In case of an error, the context is left in "undefined" state.
+     * In which case, it's necessary to reset it, before re-using it.
+     * This method can also be used to abruptly stop any unfinished decompression,
+     * and start a new one using same context resources.
+     */
+    pub fn LZ4F_resetDecompressionContext(dctx: Context);
 
 
+    /**********************************
+     * Dictionary compression
+     * ********************************/
 
+    /* These are part of the stable API since lz4 1.10.0, so they are only declared for the bundled
+     * sources and a system liblz4 that recent. A frame compressed with a dictionary names it with
+     * the dictID of its frame info. */
+
+    // LZ4F_CDict* LZ4F_createCDict(const void* dictBuffer, size_t dictSize);
+    // void        LZ4F_freeCDict(LZ4F_CDict* CDict);
+    /* When compressing multiple messages / blocks using the same dictionary, it's recommended to
+     * load it just once. LZ4F_createCDict() will create a digested dictionary, ready to start
+     * future compression operations without startup delay.
+     * LZ4F_CDict can be created once and shared by multiple threads concurrently, since its usage
+     * is read-only.
+     * dictBuffer can be released after LZ4F_CDict creation, since its content is copied within
+     * CDict.
+     */
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_createCDict(dictBuffer: *const c_void, dictSize: size_t) -> CDict;
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_freeCDict(CDict: CDict);
+
+    // size_t LZ4F_compressFrame_usingCDict(LZ4F_cctx* cctx, void* dst, size_t dstCapacity,
+    //                                      const void* src, size_t srcSize,
+    //                                      const LZ4F_CDict* cdict,
+    //                                      const LZ4F_preferences_t* preferencesPtr);
+    /* Compress an entire srcBuffer into a valid LZ4 frame using a digested Dictionary.
+     * cctx must point to a context created by LZ4F_createCompressionContext().
+     * If cdict==NULL, compress without a dictionary.
+     * dstBuffer MUST be >= LZ4F_compressFrameBound(srcSize, preferencesPtr).
+     */
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_compressFrame_usingCDict(cctx: Context,
+                                         dst: *mut c_void,
+                                         dstCapacity: size_t,
+                                         src: *const c_void,
+                                         srcSize: size_t,
+                                         cdict: CDict,
+                                         prefsPtr: *const FramePreferences) -> size_t;
+
+    // size_t LZ4F_compressBegin_usingDict(LZ4F_cctx* cctx, void* dstBuffer, size_t dstCapacity,
+    //                                     const void* dictBuffer, size_t dictSize,
+    //                                     const LZ4F_preferences_t* prefsPtr);
+    // size_t LZ4F_compressBegin_usingCDict(LZ4F_cctx* cctx, void* dstBuffer, size_t dstCapacity,
+    //                                      const LZ4F_CDict* cdict,
+    //                                      const LZ4F_preferences_t* prefsPtr);
+    /* Inits dictionary compression streaming, and writes the frame header into dstBuffer.
+     * dstCapacity must be >= LZ4F_HEADER_SIZE_MAX bytes.
+     * The result is the number of bytes written into dstBuffer for the header, or an error code
+     * (which can be tested using LZ4F_isError()).
+     */
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_compressBegin_usingDict(cctx: Context,
+                                        dstBuffer: *mut c_void,
+                                        dstCapacity: size_t,
+                                        dictBuffer: *const c_void,
+                                        dictSize: size_t,
+                                        prefsPtr: *const FramePreferences) -> size_t;
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_compressBegin_usingCDict(cctx: Context,
+                                         dstBuffer: *mut c_void,
+                                         dstCapacity: size_t,
+                                         cdict: CDict,
+                                         prefsPtr: *const FramePreferences) -> size_t;
+
+    // size_t LZ4F_decompress_usingDict(LZ4F_dctx* dctxPtr, void* dstBuffer, size_t* dstSizePtr,
+    //                                  const void* srcBuffer, size_t* srcSizePtr,
+    //                                  const void* dict, size_t dictSize,
+    //                                  const LZ4F_decompressOptions_t* decompressOptionsPtr);
+    /* Same as LZ4F_decompress(), using a predefined dictionary.
+     * Dictionary is used "in place", without any preprocessing.
+     * It must remain accessible throughout the entire frame decoding.
+     */
+    #[cfg(lz4_v1_10)]
+    pub fn LZ4F_decompress_usingDict(dctxPtr: Context,
+                                     dstBuffer: *mut c_void,
+                                     dstSizePtr: *mut size_t,
+                                     srcBuffer: *const c_void,
+                                     srcSizePtr: *mut size_t,
+                                     dict: *const c_void,
+                                     dictSize: size_t,
+                                     dOptPtr: *const FrameDecompressOptions) -> size_t;
+}
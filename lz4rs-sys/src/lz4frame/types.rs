@@ -0,0 +1,378 @@
+//! Rust wrappers around the types, enums, and structs defined in `lz4frame.h`
+
+#![allow(unstable)]
+#![allow(non_snake_case)]
+#![allow(unused_imports)]
+
+extern crate libc;
+
+use libc::{c_uint, c_int, size_t, c_char, c_void};
+
+use std::ptr;
+use std::default::Default;
+
+pub type FrameErrorCode = size_t;
+
+// TODO: Update Lz4Errors to be more like IoErrors
+/// The error codes of lz4frame, negated in the `size_t` results, numbered the way lz4 1.8 and
+/// later do. The last four only exist since lz4 1.10.
+pub enum FrameErrorKind {
+    OkNoError = 0,
+    Generic = 1,
+    InvalidMaxBlockSize = 2,
+    InvalidBlockMode = 3,
+    InvalidParameter = 4,
+    InvalidCompressionLevel = 5,
+    WrongHeaderVersion = 6,
+    InvalidBlockChecksum = 7,
+    ReservedFlagSet = 8,
+    FailedAllocation = 9,
+    SrcSizeTooLarge = 10,
+    DstMaxSizeTooSmall = 11,
+    IncompleteFrameHeader = 12,
+    UnknownFrameType = 13,
+    WrongFrameSize = 14,
+    WrongSrcPtr = 15,
+    FailedDecompression = 16,
+    InvalidHeaderChecksum = 17,
+    InvalidContentChecksum = 18,
+    FrameDecodingAlreadyStarted = 19,
+    UninitializedCompressionState = 20,
+    NullParameter = 21,
+    IoWrite = 22,
+    IoRead = 23,
+}
+
+/// The code lz4frame returns for a block whose checksum doesn't match its data.
+pub const BLOCK_CHECKSUM_INVALID: FrameErrorCode =
+    -(FrameErrorKind::InvalidBlockChecksum as isize) as FrameErrorCode;
+/// The code lz4frame returns for a frame whose content checksum doesn't match its content.
+pub const CONTENT_CHECKSUM_INVALID: FrameErrorCode =
+    -(FrameErrorKind::InvalidContentChecksum as isize) as FrameErrorCode;
+
+pub type Context = *mut c_void;
+
+/// A digested dictionary from `LZ4F_createCDict()`, which can be shared by compression contexts.
+pub type CDict = *mut c_void;
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum BlockSize {
+    Default = 0,
+    Max64KB = 4,
+    Max256KB = 5,
+    Max1MB = 6,
+    Max4MB = 7,
+}
+
+impl BlockSize {
+    /// Returns the maximum number of uncompressed bytes in a block of this size. `Default` blocks
+    /// are 64 KB.
+    pub fn bytes(&self) -> usize {
+        match *self {
+            BlockSize::Default => 64 * 1024,
+            BlockSize::Max64KB => 64 * 1024,
+            BlockSize::Max256KB => 256 * 1024,
+            BlockSize::Max1MB => 1024 * 1024,
+            BlockSize::Max4MB => 4 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum BlockMode {
+    Linked = 0,
+    Independent,
+}
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum ContentChecksum {
+    Disabled = 0,
+    Enabled,
+}
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum BlockChecksum {
+    Disabled = 0,
+    Enabled,
+}
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum FrameType {
+    Default = 0,
+    SkippableFrame,
+}
+
+#[derive(Show, Copy, Clone)]
+#[repr(C)]
+pub struct FrameInfo {
+    pub block_size_id: BlockSize,
+    pub block_mode: BlockMode,
+    pub content_checksum_flag: ContentChecksum,
+    pub frame_type: FrameType,
+    pub content_size: u64,
+    /// ID of the dictionary the frame is compressed with, 0 if none.
+    pub dict_id: c_uint,
+    /// Whether every block is followed by an xxh32 checksum of its data.
+    pub block_checksum_flag: BlockChecksum,
+}
+
+impl FrameInfo {
+    fn new(bsize: BlockSize,
+           bmode: BlockMode,
+           checksum_flag: bool,
+           ftype: FrameType,
+           content_size: usize) -> FrameInfo {
+        FrameInfo {
+            block_size_id: bsize,
+            block_mode: bmode,
+            content_checksum_flag: if checksum_flag { ContentChecksum::Enabled }
+                                   else { ContentChecksum::Disabled },
+            frame_type: ftype,
+            content_size: content_size as u64,
+            dict_id: 0,
+            block_checksum_flag: BlockChecksum::Disabled,
+        }
+    }
+
+    /// Sets the ID of the dictionary the frame is compressed with, `None` to leave it out.
+    pub fn with_dict_id(mut self, dict_id: Option<u32>) -> FrameInfo {
+        self.dict_id = dict_id.unwrap_or(0);
+        self
+    }
+
+    /// Returns the ID of the dictionary the frame is compressed with, if the header names one.
+    pub fn dictionary_id(&self) -> Option<u32> {
+        if self.dict_id != 0 { Some(self.dict_id) } else { None }
+    }
+}
+
+impl Default for FrameInfo {
+    fn default() -> FrameInfo {
+        FrameInfo {
+            block_size_id: BlockSize::Default,
+            block_mode: BlockMode::Linked,
+            content_checksum_flag: ContentChecksum::Disabled,
+            frame_type: FrameType::Default,
+            content_size: 0,
+            dict_id: 0,
+            block_checksum_flag: BlockChecksum::Disabled,
+        }
+    }
+}
+
+
+#[repr(C)]
+pub struct FramePreferences {
+    pub frame_info: FrameInfo,
+    pub compression_level: c_uint,
+    pub auto_flush: c_uint,
+    /// 1 == favor decompression speed over compression ratio, which only makes a difference to
+    /// the high compression levels.
+    pub favor_dec_speed: c_uint,
+    pub reserved: [c_uint; 3],
+}
+
+impl FramePreferences {
+    fn new(finfo: FrameInfo, compress_lvl: usize, auto_flush: bool) -> FramePreferences {
+        FramePreferences {
+            frame_info: finfo,
+            compression_level: if compress_lvl > 16 { 16 } else { compress_lvl as c_uint },
+            auto_flush: if auto_flush { 1 } else { 0 },
+            favor_dec_speed: 0,
+            reserved: [0; 3],
+        }
+    }
+
+    /// Sets whether the compressor favors decompression speed over compression ratio.
+    pub fn with_favor_dec_speed(mut self, favor: bool) -> FramePreferences {
+        self.favor_dec_speed = if favor { 1 } else { 0 };
+        self
+    }
+}
+
+impl Default for FramePreferences {
+    fn default() -> FramePreferences {
+        FramePreferences {
+            frame_info: Default::default(),
+            compression_level: 0,
+            auto_flush: 0,
+            favor_dec_speed: 0,
+            reserved: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+pub struct FrameCompressOptions {
+    /// 1 == src content will remain available on future calls to LZ4F_compress(); avoid saving
+    /// src content within tmp buffer as future dictionary
+    stable_src: c_uint,
+    pub reserved: [c_uint; 3],
+}
+
+impl Default for FrameCompressOptions {
+    fn default() -> FrameCompressOptions {
+        FrameCompressOptions {
+            stable_src: 0,
+            reserved: [0; 3],
+        }
+    }
+}
+
+impl FrameCompressOptions {
+    /// Creates options with `stable_src` set to `src_stable`. With linked blocks, lz4frame then
+    /// keeps pointing into the source buffer of a call as the dictionary, so the caller must keep
+    /// that buffer alive and unchanged until the next block is compressed or the frame is ended.
+    pub unsafe fn new(src_stable: bool) -> FrameCompressOptions {
+        FrameCompressOptions {
+            stable_src: if src_stable { 1 } else { 0 },
+            reserved: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+pub struct FrameDecompressOptions {
+    /// guarantee that decompresed data will still be there on next function calls (avoid storage
+    /// into tmp buffers)
+    stable_dst: c_uint,
+    /// 1 == don't verify checksums, in lz4 1.9.4 and later. Always 0 here; the slot is only named
+    /// so that nothing else takes it.
+    skip_checksums: c_uint,
+    reserved: [c_uint; 2],
+}
+
+impl Default for FrameDecompressOptions {
+    fn default() -> FrameDecompressOptions {
+        FrameDecompressOptions {
+            stable_dst: 0,
+            skip_checksums: 0,
+            reserved: [0; 2],
+        }
+    }
+}
+
+impl FrameDecompressOptions {
+    /// Creates options with `stable_dst` set to `dst_stable`. lz4frame then keeps pointing into the
+    /// destination buffer of a call as the dictionary for the following blocks, so the caller must
+    /// keep that buffer alive and unchanged until the frame is decompressed to its end.
+    pub unsafe fn new(dst_stable: bool) -> FrameDecompressOptions {
+        FrameDecompressOptions {
+            stable_dst: if dst_stable { 1 } else { 0 },
+            skip_checksums: 0,
+            reserved: [0; 2],
+        }
+    }
+}
+
+
+/// Checks that the `#[repr(C)]` structs are laid out like their counterparts in `lz4frame.h`.
+mod layout_tests {
+    use std::mem::size_of;
+    use std::default::Default;
+    use libc::size_t;
+    use super::{
+        BlockSize,
+        BlockMode,
+        ContentChecksum,
+        BlockChecksum,
+        FrameType,
+        FrameInfo,
+        FramePreferences,
+        FrameCompressOptions,
+        FrameDecompressOptions,
+    };
+
+    #[cfg(not(feature = "types-only"))]
+    extern {
+        fn lz4rs_sys_sizeof_frame_info() -> size_t;
+        fn lz4rs_sys_sizeof_preferences() -> size_t;
+        fn lz4rs_sys_sizeof_compress_options() -> size_t;
+        fn lz4rs_sys_sizeof_decompress_options() -> size_t;
+        fn lz4rs_sys_offsetof_content_size() -> size_t;
+        fn lz4rs_sys_offsetof_compression_level() -> size_t;
+        fn lz4rs_sys_offsetof_auto_flush() -> size_t;
+        fn lz4rs_sys_offsetof_dict_id() -> size_t;
+        fn lz4rs_sys_offsetof_block_checksum_flag() -> size_t;
+        fn lz4rs_sys_offsetof_favor_dec_speed() -> size_t;
+        fn lz4rs_sys_offsetof_skip_checksums() -> size_t;
+    }
+
+    /// Returns the offset of `field` within `base` in bytes.
+    fn offset<T, F>(base: &T, field: &F) -> usize {
+        (field as *const F as usize) - (base as *const T as usize)
+    }
+
+    /// The enums stand in for C enums, so they have to be as large as an `int`.
+    #[test]
+    fn enums_are_int_sized() {
+        assert_eq!(size_of::<BlockSize>(), 4);
+        assert_eq!(size_of::<BlockMode>(), 4);
+        assert_eq!(size_of::<ContentChecksum>(), 4);
+        assert_eq!(size_of::<BlockChecksum>(), 4);
+        assert_eq!(size_of::<FrameType>(), 4);
+    }
+
+    #[test]
+    fn frame_info_layout() {
+        let finfo: FrameInfo = Default::default();
+        assert_eq!(size_of::<FrameInfo>(), 32);
+        assert_eq!(offset(&finfo, &finfo.content_size), 16);
+        assert_eq!(offset(&finfo, &finfo.dict_id), 24);
+        assert_eq!(offset(&finfo, &finfo.block_checksum_flag), 28);
+    }
+
+    #[test]
+    fn preferences_layout() {
+        let prefs: FramePreferences = Default::default();
+        assert_eq!(size_of::<FramePreferences>(), 56);
+        assert_eq!(offset(&prefs, &prefs.compression_level), 32);
+        assert_eq!(offset(&prefs, &prefs.auto_flush), 36);
+        assert_eq!(offset(&prefs, &prefs.favor_dec_speed), 40);
+    }
+
+    #[test]
+    fn options_layout() {
+        let opts: FrameDecompressOptions = Default::default();
+        assert_eq!(size_of::<FrameCompressOptions>(), 16);
+        assert_eq!(size_of::<FrameDecompressOptions>(), 16);
+        assert_eq!(offset(&opts, &opts.skip_checksums), 4);
+    }
+
+    /// Compares the layouts with what the C compiler makes of `lz4frame.h`. With a system lz4frame
+    /// older than 1.9.4, `skip_checksums` is compared with the reserved slot it took.
+    #[test]
+    #[cfg(all(not(feature = "types-only"), not(miri)))]
+    fn matches_lz4frame_h() {
+        let finfo: FrameInfo = Default::default();
+        let prefs: FramePreferences = Default::default();
+        let opts: FrameDecompressOptions = Default::default();
+        unsafe {
+            assert_eq!(size_of::<FrameInfo>(), lz4rs_sys_sizeof_frame_info() as usize);
+            assert_eq!(size_of::<FramePreferences>(), lz4rs_sys_sizeof_preferences() as usize);
+            assert_eq!(size_of::<FrameCompressOptions>(),
+                       lz4rs_sys_sizeof_compress_options() as usize);
+            assert_eq!(size_of::<FrameDecompressOptions>(),
+                       lz4rs_sys_sizeof_decompress_options() as usize);
+            assert_eq!(offset(&finfo, &finfo.content_size),
+                       lz4rs_sys_offsetof_content_size() as usize);
+            assert_eq!(offset(&prefs, &prefs.compression_level),
+                       lz4rs_sys_offsetof_compression_level() as usize);
+            assert_eq!(offset(&prefs, &prefs.auto_flush),
+                       lz4rs_sys_offsetof_auto_flush() as usize);
+            assert_eq!(offset(&finfo, &finfo.dict_id),
+                       lz4rs_sys_offsetof_dict_id() as usize);
+            assert_eq!(offset(&finfo, &finfo.block_checksum_flag),
+                       lz4rs_sys_offsetof_block_checksum_flag() as usize);
+            assert_eq!(offset(&prefs, &prefs.favor_dec_speed),
+                       lz4rs_sys_offsetof_favor_dec_speed() as usize);
+            assert_eq!(offset(&opts, &opts.skip_checksums),
+                       lz4rs_sys_offsetof_skip_checksums() as usize);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Rust bindings to the functions in `lz4hc.h`
+
+#![allow(unstable)]
+#![allow(non_snake_case)]
+#![allow(unused_imports)]
+
+extern crate libc;
+
+use libc::{c_int, size_t, c_char, c_void};
+
+/// Size in bytes of the HC stream state, as lz4 1.8.2 and later reserve it.
+pub const LZ4_STREAMHCSIZE: usize = 262200;
+#[cfg(target_pointer_width = "64")]
+pub const LZ4_STREAMHCSIZE_SIZET: usize = LZ4_STREAMHCSIZE / 8;
+#[cfg(target_pointer_width = "32")]
+pub const LZ4_STREAMHCSIZE_SIZET: usize = LZ4_STREAMHCSIZE / 4;
+
+/// State of an HC compression stream. It can be allocated statically, but has to be initialized
+/// with `LZ4_resetStreamHC()` before its first use.
+#[repr(C)]
+pub struct LZ4_streamHC_t {
+    pub table: [size_t; LZ4_STREAMHCSIZE_SIZET],
+}
+
+extern {
+    // int LZ4_compress_HC (const char* src, char* dst, int srcSize, int maxDstSize,
+    //                      int compressionLevel);
+    /* LZ4_compress_HC()
+     * Destination buffer 'dst' must be already allocated.
+     * Compression completion is guaranteed if 'dst' buffer is sized to handle worst circumstances
+     * (data not compressible). Worst size evaluation is provided by function LZ4_compressBound()
+     * (see "lz4.h").
+     * srcSize : Max supported value is LZ4_MAX_INPUT_SIZE (see "lz4.h")
+     * compressionLevel : Recommended values are between 4 and 9, although any value between 0
+     *                    and 16 will work. 0 means "use default value" (see lz4hc.c).
+     *                    Values >16 behave the same as 16.
+     * return : the number of bytes written into buffer 'dst'
+     *          or 0 if compression fails.
+     */
+    pub fn LZ4_compress_HC(src: *const c_char,
+                           dst: *mut c_char,
+                           srcSize: c_int,
+                           maxDstSize: c_int,
+                           compressionLevel: c_int) -> c_int;
+
+    // int LZ4_sizeofStateHC(void);
+    // int LZ4_compress_HC_extStateHC(void* state, const char* src, char* dst, int srcSize,
+    //                                int maxDstSize, int compressionLevel);
+    /* LZ4_compress_HC_extStateHC()
+     * Use this function if you prefer to manually allocate memory for compression tables.
+     * To know how much memory must be allocated for the compression tables, use :
+     * int LZ4_sizeofStateHC();
+     * Allocated memory must be aligned on 8-bytes boundaries (which a normal malloc() will do
+     * properly).
+     */
+    pub fn LZ4_sizeofStateHC() -> c_int;
+    pub fn LZ4_compress_HC_extStateHC(state: *mut c_void,
+                                      src: *const c_char,
+                                      dst: *mut c_char,
+                                      srcSize: c_int,
+                                      maxDstSize: c_int,
+                                      compressionLevel: c_int) -> c_int;
+
+
+    /**************************************
+     * Streaming Compression
+     * ************************************/
+
+    // LZ4_streamHC_t* LZ4_createStreamHC(void);
+    // int             LZ4_freeStreamHC (LZ4_streamHC_t* streamHCPtr);
+    /* These functions create and release memory for LZ4 HC streaming state.
+     * Newly created states are already initialized.
+     * Existing state space can be re-used anytime using LZ4_resetStreamHC().
+     */
+    pub fn LZ4_createStreamHC() -> *mut LZ4_streamHC_t;
+    pub fn LZ4_freeStreamHC(streamHCPtr: *mut LZ4_streamHC_t) -> c_int;
+
+    // void LZ4_resetStreamHC (LZ4_streamHC_t* streamHCPtr, int compressionLevel);
+    // int  LZ4_loadDictHC (LZ4_streamHC_t* streamHCPtr, const char* dictionary, int dictSize);
+    pub fn LZ4_resetStreamHC(streamHCPtr: *mut LZ4_streamHC_t, compressionLevel: c_int);
+    pub fn LZ4_loadDictHC(streamHCPtr: *mut LZ4_streamHC_t,
+                          dictionary: *const c_char,
+                          dictSize: c_int) -> c_int;
+
+    // int LZ4_compress_HC_continue (LZ4_streamHC_t* streamHCPtr, const char* src, char* dst,
+    //                               int srcSize, int maxDstSize);
+    /* LZ4_compress_HC_continue()
+     * Compresses 'src' using data from previously compressed blocks as dictionary. Previous
+     * blocks must remain available and unmodified, or be saved with LZ4_saveDictHC().
+     */
+    pub fn LZ4_compress_HC_continue(streamHCPtr: *mut LZ4_streamHC_t,
+                                    src: *const c_char,
+                                    dst: *mut c_char,
+                                    srcSize: c_int,
+                                    maxDstSize: c_int) -> c_int;
+
+    // int LZ4_saveDictHC (LZ4_streamHC_t* streamHCPtr, char* safeBuffer, int maxDictSize);
+    pub fn LZ4_saveDictHC(streamHCPtr: *mut LZ4_streamHC_t,
+                          safeBuffer: *mut c_char,
+                          maxDictSize: c_int) -> c_int;
+
+
+    /**************************************
+     * Obsolete Functions
+     * ************************************/
+
+    /* These functions are still part of lz4hc.h, but are superseded by the ones above. */
+    pub fn LZ4_compressHC(source: *const c_char, dest: *mut c_char, inputSize: c_int) -> c_int;
+    pub fn LZ4_compressHC_limitedOutput(source: *const c_char,
+                                        dest: *mut c_char,
+                                        inputSize: c_int,
+                                        maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_compressHC2(source: *const c_char,
+                           dest: *mut c_char,
+                           inputSize: c_int,
+                           compressionLevel: c_int) -> c_int;
+    pub fn LZ4_compressHC2_limitedOutput(source: *const c_char,
+                                         dest: *mut c_char,
+                                         inputSize: c_int,
+                                         maxOutputSize: c_int,
+                                         compressionLevel: c_int) -> c_int;
+    pub fn LZ4_compressHC_withStateHC(state: *mut c_void,
+                                      source: *const c_char,
+                                      dest: *mut c_char,
+                                      inputSize: c_int) -> c_int;
+    pub fn LZ4_compressHC_limitedOutput_withStateHC(state: *mut c_void,
+                                                    source: *const c_char,
+                                                    dest: *mut c_char,
+                                                    inputSize: c_int,
+                                                    maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_compressHC2_withStateHC(state: *mut c_void,
+                                       source: *const c_char,
+                                       dest: *mut c_char,
+                                       inputSize: c_int,
+                                       compressionLevel: c_int) -> c_int;
+    pub fn LZ4_compressHC2_limitedOutput_withStateHC(state: *mut c_void,
+                                                     source: *const c_char,
+                                                     dest: *mut c_char,
+                                                     inputSize: c_int,
+                                                     maxOutputSize: c_int,
+                                                     compressionLevel: c_int) -> c_int;
+    pub fn LZ4_compressHC_continue(LZ4_streamHCPtr: *mut LZ4_streamHC_t,
+                                   source: *const c_char,
+                                   dest: *mut c_char,
+                                   inputSize: c_int) -> c_int;
+    pub fn LZ4_compressHC_limitedOutput_continue(LZ4_streamHCPtr: *mut LZ4_streamHC_t,
+                                                 source: *const c_char,
+                                                 dest: *mut c_char,
+                                                 inputSize: c_int,
+                                                 maxOutputSize: c_int) -> c_int;
+    pub fn LZ4_createHC(inputBuffer: *mut c_char) -> *mut c_void;
+    pub fn LZ4_freeHC(LZ4HC_Data: *mut c_void) -> c_int;
+    pub fn LZ4_slideInputBufferHC(LZ4HC_Data: *mut c_void) -> *mut c_char;
+    pub fn LZ4_compressHC2_continue(LZ4HC_Data: *mut c_void,
+                                    source: *const c_char,
+                                    dest: *mut c_char,
+                                    inputSize: c_int,
+                                    compressionLevel: c_int) -> c_int;
+    pub fn LZ4_compressHC2_limitedOutput_continue(LZ4HC_Data: *mut c_void,
+                                                  source: *const c_char,
+                                                  dest: *mut c_char,
+                                                  inputSize: c_int,
+                                                  maxOutputSize: c_int,
+                                                  compressionLevel: c_int) -> c_int;
+    pub fn LZ4_sizeofStreamStateHC() -> c_int;
+    pub fn LZ4_resetStreamStateHC(state: *mut c_void, inputBuffer: *mut c_char) -> c_int;
+}
@@ -0,0 +1,85 @@
+//! Rust bindings to the functions in `xxhash.h`
+
+#![allow(unstable)]
+#![allow(non_snake_case)]
+#![allow(unused_imports)]
+
+extern crate libc;
+
+use libc::{c_uint, c_ulonglong, c_longlong, size_t, c_void};
+
+#[derive(Show, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub enum XXH_errorcode {
+    XXH_OK = 0,
+    XXH_ERROR,
+}
+
+/// State of a streaming xxh32 hash. It can be allocated statically and initialized with
+/// `XXH32_reset()`.
+#[repr(C)]
+pub struct XXH32_state_t {
+    pub ll: [c_longlong; 6],
+}
+
+/// State of a streaming xxh64 hash. It can be allocated statically and initialized with
+/// `XXH64_reset()`.
+#[repr(C)]
+pub struct XXH64_state_t {
+    pub ll: [c_longlong; 11],
+}
+
+extern {
+    // unsigned int       XXH32 (const void* input, size_t length, unsigned seed);
+    // unsigned long long XXH64 (const void* input, size_t length, unsigned long long seed);
+    /* XXH32() :
+     * Calculate the 32-bits hash of sequence "length" bytes stored at memory address "input".
+     * The memory between input & input+length must be valid (allocated and read-accessible).
+     * "seed" can be used to alter the result predictably.
+     * XXH64() :
+     * Calculate the 64-bits hash of sequence of length "len" stored at memory address "input".
+     */
+    pub fn XXH32(input: *const c_void, length: size_t, seed: c_uint) -> c_uint;
+    pub fn XXH64(input: *const c_void, length: size_t, seed: c_ulonglong) -> c_ulonglong;
+
+
+    /*****************************
+     * Advanced Hash Functions
+     * ***************************/
+
+    // XXH32_state_t* XXH32_createState(void);
+    // XXH_errorcode  XXH32_freeState(XXH32_state_t* statePtr);
+    // XXH64_state_t* XXH64_createState(void);
+    // XXH_errorcode  XXH64_freeState(XXH64_state_t* statePtr);
+    /* These functions create and release memory for XXH state.
+     * States must then be initialized using XXHnn_reset() before first use.
+     */
+    pub fn XXH32_createState() -> *mut XXH32_state_t;
+    pub fn XXH32_freeState(statePtr: *mut XXH32_state_t) -> XXH_errorcode;
+    pub fn XXH64_createState() -> *mut XXH64_state_t;
+    pub fn XXH64_freeState(statePtr: *mut XXH64_state_t) -> XXH_errorcode;
+
+    // XXH_errorcode XXH32_reset  (XXH32_state_t* statePtr, unsigned seed);
+    // XXH_errorcode XXH32_update (XXH32_state_t* statePtr, const void* input, size_t length);
+    // unsigned int  XXH32_digest (const XXH32_state_t* statePtr);
+    /* These functions calculate the xxHash of an input provided in multiple smaller packets,
+     * as opposed to an input provided as a single block.
+     * XXH32_digest() can be called at any time; it doesn't modify the state, so hashing can
+     * continue afterwards.
+     */
+    pub fn XXH32_reset(statePtr: *mut XXH32_state_t, seed: c_uint) -> XXH_errorcode;
+    pub fn XXH32_update(statePtr: *mut XXH32_state_t,
+                        input: *const c_void,
+                        length: size_t) -> XXH_errorcode;
+    pub fn XXH32_digest(statePtr: *const XXH32_state_t) -> c_uint;
+
+    // XXH_errorcode      XXH64_reset  (XXH64_state_t* statePtr, unsigned long long seed);
+    // XXH_errorcode      XXH64_update (XXH64_state_t* statePtr, const void* input,
+    //                                  size_t length);
+    // unsigned long long XXH64_digest (const XXH64_state_t* statePtr);
+    pub fn XXH64_reset(statePtr: *mut XXH64_state_t, seed: c_ulonglong) -> XXH_errorcode;
+    pub fn XXH64_update(statePtr: *mut XXH64_state_t,
+                        input: *const c_void,
+                        length: size_t) -> XXH_errorcode;
+    pub fn XXH64_digest(statePtr: *const XXH64_state_t) -> c_ulonglong;
+}
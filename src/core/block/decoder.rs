@@ -0,0 +1,158 @@
+//! A safe Rust implementation of `LZ4_decompress_safe` and `LZ4_decompress_safe_partial`, which
+//! backs the decompression functions in `core::block::rust`. Valid blocks decode to exactly the
+//! same bytes as with the C implementation and are bounds checked the same way. Matches with an
+//! offset of 0, which the C implementation decodes to unspecified data, are rejected as malformed.
+
+#![forbid(unsafe_code)]
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::slice::bytes::copy_memory;
+
+use super::super::super::frame::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+/// Every match is at least this long; a token stores the match length minus `MIN_MATCH`.
+const MIN_MATCH: usize = 4;
+
+/// The last 5 bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+
+/// The last match has to start at least 12 bytes before the end of a block.
+const MF_LIMIT: usize = 12;
+
+/// Value of the literal length half of a token meaning more length bytes follow.
+const RUN_MASK: usize = 15;
+
+/// Value of the match length half of a token meaning more length bytes follow.
+const ML_MASK: usize = 15;
+
+/// Decompresses the block in `src` into `dst`, returning the number of bytes written.
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+    decompress_with_prefix(src, dst, 0)
+}
+
+/// Decompresses the block in `src` into `dst`, starting at `prefix_len`. Matches may reach back
+/// into the first `prefix_len` bytes of `dst`, which have to hold the data that came right before
+/// the block, e.g. the previous block of a frame with linked blocks.
+/// Returns the number of bytes written after the prefix.
+pub fn decompress_with_prefix(src: &[u8], dst: &mut [u8], prefix_len: usize) -> Lz4Result<usize> {
+    decode(src, dst, prefix_len, false)
+}
+
+/// Decompresses the start of the block in `src` into `dst`, stopping as soon as `dst` is full
+/// instead of decoding the rest of the block. Returns the number of bytes written, which is less
+/// than `dst.len()` only if the whole block is shorter.
+pub fn decompress_partial(src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+    decode(src, dst, 0, true)
+}
+
+/// Decodes the block in `src` into `dst` after `prefix_len` bytes of prefix. With `partial`,
+/// decoding stops once `dst` is full, and the end of `dst` doesn't have to line up with the end
+/// of the block.
+fn decode(src: &[u8], dst: &mut [u8], prefix_len: usize, partial: bool) -> Lz4Result<usize> {
+    let iend: usize = src.len();
+    let oend: usize = dst.len();
+
+    if prefix_len > oend {
+        return Err(Lz4Error { desc: "block prefix larger than destination".to_string() });
+    }
+    // nothing to write, so the block has to be a single empty sequence
+    if prefix_len == oend {
+        if partial { return Ok(0); }
+        return if iend == 1 && src[0] == 0 { Ok(0) } else { Err(malformed(0)) };
+    }
+
+    let mut ip: usize = 0;
+    let mut op: usize = prefix_len;
+
+    loop {
+        if ip >= iend { return Err(malformed(ip)); }
+        let token: usize = src[ip] as usize;
+        ip += 1;
+
+        // literal length
+        let mut length: usize = token >> 4;
+        if length == RUN_MASK {
+            loop {
+                if ip >= iend { return Err(malformed(ip)); }
+                let s: usize = src[ip] as usize;
+                ip += 1;
+                length += s;
+                if s != 255 || ip + RUN_MASK >= iend { break; }
+            }
+        }
+
+        let cpy: usize = op + length;
+        if partial {
+            // stop once `dst` is full, or at the last sequence, which ends exactly at the end of
+            // `src`
+            if ip + length > iend { return Err(malformed(ip)); }
+            if cpy >= oend {
+                copy_memory(dst.slice_mut(op, oend), src.slice(ip, ip + oend - op));
+                op = oend;
+                break;
+            }
+            if ip + length == iend {
+                copy_memory(dst.slice_mut(op, cpy), src.slice(ip, iend));
+                op = cpy;
+                break;
+            }
+            if ip + length + 2 > iend { return Err(malformed(ip + length)); }
+        } else if cpy + MF_LIMIT > oend || ip + length + 2 + 1 + LAST_LITERALS > iend {
+            // too close to the end of either buffer for another match means this has to be the
+            // last sequence, which ends exactly at the end of `src`
+            if ip + length != iend || cpy > oend { return Err(malformed(ip)); }
+            copy_memory(dst.slice_mut(op, cpy), src.slice(ip, iend));
+            op = cpy;
+            break;
+        }
+        copy_memory(dst.slice_mut(op, cpy), src.slice(ip, ip + length));
+        ip += length;
+        op = cpy;
+
+        // match offset
+        let offset: usize = (src[ip] as usize) | ((src[ip + 1] as usize) << 8);
+        ip += 2;
+        if offset == 0 || offset > op { return Err(malformed(ip - 2)); }
+
+        // match length
+        let mut length: usize = token & ML_MASK;
+        if length == ML_MASK {
+            loop {
+                if ip + LAST_LITERALS > iend { return Err(malformed(ip)); }
+                let s: usize = src[ip] as usize;
+                ip += 1;
+                length += s;
+                if s != 255 { break; }
+            }
+        }
+        length += MIN_MATCH;
+
+        // the last `LAST_LITERALS` bytes have to be literals
+        let mut cpy: usize = op + length;
+        if partial {
+            if cpy > oend { cpy = oend; }
+        } else if cpy + LAST_LITERALS > oend {
+            return Err(malformed(ip));
+        }
+
+        // copy the match a byte at a time, since it may overlap the bytes being written
+        let mut m: usize = op - offset;
+        while op < cpy {
+            dst[op] = dst[m];
+            op += 1;
+            m += 1;
+        }
+        if partial && op == oend { break; }
+    }
+    Ok(op - prefix_len)
+}
+
+/// Creates the error for a block found to be malformed at byte `pos` of its input.
+fn malformed(pos: usize) -> Lz4Error {
+    Lz4Error { desc: format!("malformed block at byte {}", pos) }
+}
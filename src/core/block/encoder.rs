@@ -0,0 +1,287 @@
+//! A safe Rust implementation of `LZ4_compress_fast` and `LZ4_compress_destSize`, which backs the
+//! compression functions in `core::block::rust`. It uses the same greedy, single pass hash table
+//! search as the C implementation, so the output is similar in size but not necessarily byte for
+//! byte identical. Either way, the output is a valid block any LZ4 decoder accepts.
+
+#![forbid(unsafe_code)]
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::slice::bytes::copy_memory;
+use std::iter::repeat;
+
+use super::super::super::frame::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+/// Every match is at least this long; a token stores the match length minus `MIN_MATCH`.
+const MIN_MATCH: usize = 4;
+
+/// The last 5 bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+
+/// The last match has to start at least 12 bytes before the end of a block.
+const MF_LIMIT: usize = 12;
+
+/// Matches can be at most this far behind the bytes they are repeated at.
+const MAX_DISTANCE: usize = 65535;
+
+/// Value of either half of a token meaning more length bytes follow.
+const RUN_MASK: usize = 15;
+
+/// The hash table has `1 << HASH_LOG` entries, like `LZ4_MEMORY_USAGE` 14 in `lz4.h`.
+const HASH_LOG: usize = 12;
+const HASH_SIZE: usize = 1 << HASH_LOG;
+
+/// Largest position a hash table entry can hold.
+const MAX_ENTRY: usize = 0xFFFFFFFF;
+
+/// The number of failed match attempts after which the search starts skipping ahead is
+/// `1 << SKIP_TRIGGER` divided by the acceleration.
+const SKIP_TRIGGER: usize = 6;
+
+/// Compresses `src` into `dst` as a single block, returning the number of bytes written.
+/// `acceleration` trades compression ratio for speed like in `LZ4_compress_fast`; each increment
+/// makes the search skip ahead faster. Values below 1 are treated as 1.
+/// Fails if `dst` is too small to hold the compressed block; `compress_bound` gives a size that
+/// always suffices.
+pub fn compress(src: &[u8], dst: &mut [u8], acceleration: usize) -> Lz4Result<usize> {
+    let mut table: [u32; HASH_SIZE] = [0; HASH_SIZE];
+    let (_, written) = try!(compress_generic(src, dst, acceleration, false, &mut table, 0));
+    Ok(written)
+}
+
+/// A hash table that is kept from one block to the next, so that compressing many small blocks
+/// neither allocates nor clears a table for each of them. Entries hold positions offset by `base`,
+/// which moves past every block compressed, so that entries left over from earlier blocks are
+/// below it and never taken for matches. Only once the offsets would overflow is the table
+/// cleared.
+pub struct HashTable {
+    entries: Vec<u32>,
+    base: usize,
+}
+
+impl HashTable {
+    /// Creates an empty `HashTable`.
+    pub fn new() -> HashTable {
+        HashTable {
+            entries: repeat(0u32).take(HASH_SIZE).collect(),
+            base: 0,
+        }
+    }
+
+    /// Compresses `src` into `dst` as a single, independent block like `compress`, using this
+    /// table.
+    pub fn compress(&mut self,
+                    src: &[u8],
+                    dst: &mut [u8],
+                    acceleration: usize) -> Lz4Result<usize> {
+        if src.len() > MAX_ENTRY - self.base {
+            for entry in self.entries.iter_mut() {
+                *entry = 0;
+            }
+            self.base = 0;
+        }
+        let result = compress_generic(src, dst, acceleration, false,
+                                      self.entries.as_mut_slice(), self.base);
+        self.base += src.len();
+        let (_, written) = try!(result);
+        Ok(written)
+    }
+}
+
+/// Compresses as much of `src` as fits into `dst` as a single block, like `LZ4_compress_destSize`.
+/// Returns the number of bytes of `src` consumed and the number of bytes written.
+pub fn compress_to_fit(src: &[u8], dst: &mut [u8]) -> Lz4Result<(usize, usize)> {
+    if dst.len() == 0 {
+        return Err(Lz4Error { desc: "destination too small to hold compressed block".to_string() });
+    }
+    let mut table: [u32; HASH_SIZE] = [0; HASH_SIZE];
+    compress_generic(src, dst, 1, true, &mut table, 0)
+}
+
+/// Compresses `src` into `dst`. With `fit`, compression stops before `dst` runs out of space
+/// instead of failing, and the block ends after however much of `src` made it in. `table` holds
+/// `HASH_SIZE` entries, which are positions in `src` plus `base`; smaller ones are ignored.
+/// Returns the number of bytes of `src` consumed and the number of bytes written.
+fn compress_generic(src: &[u8],
+                    dst: &mut [u8],
+                    acceleration: usize,
+                    fit: bool,
+                    table: &mut [u32],
+                    base: usize) -> Lz4Result<(usize, usize)> {
+    let acceleration: usize = if acceleration < 1 { 1 } else { acceleration };
+    let len: usize = src.len();
+    let oend: usize = dst.len();
+    let mut op: usize = 0;
+    let mut anchor: usize = 0;
+
+    if len >= MF_LIMIT + 1 {
+        let mf_limit: usize = len - MF_LIMIT;
+        let match_limit: usize = len - LAST_LITERALS;
+
+        table[hash(read_u32(src, 0))] = base as u32;
+        let mut ip: usize = 1;
+        let mut forward_h: usize = hash(read_u32(src, ip));
+
+        'sequences: loop {
+            // find a match, skipping ahead faster the longer none is found
+            let mut forward_ip: usize = ip;
+            let mut search_match_nb: usize = acceleration << SKIP_TRIGGER;
+            let mut candidate: usize;
+            loop {
+                let h: usize = forward_h;
+                ip = forward_ip;
+                forward_ip = ip + (search_match_nb >> SKIP_TRIGGER);
+                search_match_nb += 1;
+                if forward_ip > mf_limit { break 'sequences; }
+
+                let entry: usize = table[h] as usize;
+                table[h] = (base + ip) as u32;
+                forward_h = hash(read_u32(src, forward_ip));
+                if entry >= base {
+                    candidate = entry - base;
+                    if candidate + MAX_DISTANCE >= ip &&
+                       read_u32(src, candidate) == read_u32(src, ip) {
+                        break;
+                    }
+                }
+            }
+
+            // extend the match backwards over literals that repeat as well
+            while ip > anchor && candidate > 0 && src[ip - 1] == src[candidate - 1] {
+                ip -= 1;
+                candidate -= 1;
+            }
+
+            // literals
+            let mut token_pos: usize = op;
+            let lit_len: usize = ip - anchor;
+            if fit && !fits_sequence(op, lit_len, oend) { break 'sequences; }
+            try!(check_space(dst, op, 1 + (lit_len / 255) + 1 + lit_len));
+            op += 1;
+            if lit_len >= RUN_MASK {
+                dst[token_pos] = (RUN_MASK << 4) as u8;
+                op = write_length(dst, op, lit_len - RUN_MASK);
+            } else {
+                dst[token_pos] = (lit_len << 4) as u8;
+            }
+            copy_memory(dst.slice_mut(op, op + lit_len), src.slice(anchor, ip));
+            op += lit_len;
+
+            loop {
+                // offset
+                try!(check_space(dst, op, 2));
+                let offset: usize = ip - candidate;
+                dst[op] = offset as u8;
+                dst[op + 1] = (offset >> 8) as u8;
+                op += 2;
+
+                // match length
+                ip += MIN_MATCH;
+                candidate += MIN_MATCH;
+                let start: usize = ip;
+                while ip < match_limit && src[ip] == src[candidate] {
+                    ip += 1;
+                    candidate += 1;
+                }
+                let mut match_len: usize = ip - start;
+                // shorten the match so that the last literals still fit
+                if fit && op + 1 + LAST_LITERALS + (match_len + 240) / 255 > oend {
+                    match_len = (RUN_MASK - 1) + (oend - op - 1 - LAST_LITERALS) * 255;
+                    ip = start + match_len;
+                }
+                if match_len >= RUN_MASK {
+                    try!(check_space(dst, op, 1 + ((match_len - RUN_MASK) / 255)));
+                    dst[token_pos] += RUN_MASK as u8;
+                    op = write_length(dst, op, match_len - RUN_MASK);
+                } else {
+                    dst[token_pos] += match_len as u8;
+                }
+
+                anchor = ip;
+                if ip > mf_limit { break 'sequences; }
+
+                // fill the table and test whether the next position matches right away
+                let prev: usize = ip - 2;
+                table[hash(read_u32(src, prev))] = (base + prev) as u32;
+                let h: usize = hash(read_u32(src, ip));
+                let entry: usize = table[h] as usize;
+                table[h] = (base + ip) as u32;
+                if entry >= base && entry - base + MAX_DISTANCE >= ip &&
+                   read_u32(src, entry - base) == read_u32(src, ip) {
+                    candidate = entry - base;
+                    if fit && !fits_sequence(op, 0, oend) { break 'sequences; }
+                    try!(check_space(dst, op, 1));
+                    token_pos = op;
+                    dst[token_pos] = 0;
+                    op += 1;
+                    continue;
+                }
+
+                ip += 1;
+                forward_h = hash(read_u32(src, ip));
+                break;
+            }
+        }
+    }
+
+    // last literals, as many as fit with `fit`
+    let mut last_run: usize = len - anchor;
+    if fit && op + 1 + (last_run + 240) / 255 + last_run > oend {
+        last_run = oend - op - 1;
+        last_run -= (last_run + 256 - RUN_MASK) / 256;
+    }
+    try!(check_space(dst, op, 1 + (last_run + 240) / 255 + last_run));
+    if last_run >= RUN_MASK {
+        dst[op] = (RUN_MASK << 4) as u8;
+        op = write_length(dst, op + 1, last_run - RUN_MASK);
+    } else {
+        dst[op] = (last_run << 4) as u8;
+        op += 1;
+    }
+    copy_memory(dst.slice_mut(op, op + last_run), src.slice(anchor, anchor + last_run));
+    Ok((anchor + last_run, op + last_run))
+}
+
+/// Whether a sequence with `lit_len` literals written at `op` leaves room for its offset and for
+/// a last sequence of enough literals that its match ends at least `MF_LIMIT` bytes before the end
+/// of the block.
+fn fits_sequence(op: usize, lit_len: usize, oend: usize) -> bool {
+    op + 1 + (lit_len + 240) / 255 + lit_len + 2 + 1 + (MF_LIMIT - MIN_MATCH) <= oend
+}
+
+/// Reads 4 bytes at `pos` as a little endian integer.
+fn read_u32(src: &[u8], pos: usize) -> u32 {
+    (src[pos] as u32) | ((src[pos + 1] as u32) << 8) |
+        ((src[pos + 2] as u32) << 16) | ((src[pos + 3] as u32) << 24)
+}
+
+/// Hashes the 4 bytes `sequence` into an index into the hash table.
+fn hash(sequence: u32) -> usize {
+    let product: u32 = ((sequence as u64) * 2654435761u64) as u32;
+    (product >> (32 - HASH_LOG)) as usize
+}
+
+/// Writes the remainder `len` of a literal or match length that didn't fit into its token.
+/// Returns the position after the written bytes.
+fn write_length(dst: &mut [u8], mut op: usize, mut len: usize) -> usize {
+    while len >= 255 {
+        dst[op] = 255;
+        op += 1;
+        len -= 255;
+    }
+    dst[op] = len as u8;
+    op + 1
+}
+
+/// Makes sure that `n` more bytes fit into `dst` at `op`.
+fn check_space(dst: &[u8], op: usize, n: usize) -> Lz4Result<()> {
+    if op + n > dst.len() {
+        Err(Lz4Error { desc: "destination too small to hold compressed block".to_string() })
+    } else {
+        Ok(())
+    }
+}
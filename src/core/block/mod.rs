@@ -0,0 +1,562 @@
+//! This module contains safe wrappers around the block functions in `lz4.h`, which compress and
+//! decompress raw LZ4 blocks without any framing.
+//! With the `pure-rust` feature enabled, blocks are compressed and decompressed by the safe Rust
+//! implementation in `rust` instead, whose decoder produces the same output as
+//! `LZ4_decompress_safe`.
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+#[cfg(not(feature = "pure-rust"))]
+use libc::{c_int, c_char, c_void};
+
+#[cfg(not(feature = "pure-rust"))]
+use std::i32;
+
+#[cfg(not(feature = "pure-rust"))]
+use std::cmp::min;
+use std::iter::repeat;
+
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::{
+    LZ4_compress_fast,
+    LZ4_compress_fast_extState,
+    LZ4_sizeofState,
+    LZ4_compress_HC_extStateHC,
+    LZ4_sizeofStateHC,
+    LZ4_compress_destSize,
+    LZ4_decompress_safe,
+    LZ4_decompress_safe_partial,
+    LZ4_decompress_safe_usingDict,
+};
+
+use super::super::frame::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+#[cfg(feature = "pure-rust")]
+pub use self::rust::BlockCompressor;
+
+pub mod rust;
+
+mod encoder;
+mod decoder;
+
+/// The largest number of bytes that can be compressed into a single block.
+pub const MAX_INPUT_SIZE: usize = 0x7E000000;
+
+/// The size of the little endian uncompressed size in front of a size prepended block.
+pub const SIZE_PREFIX_LEN: usize = 4;
+
+/// The acceleration `LZ4_compress_default` uses, i.e. the best compression ratio `compress` can
+/// achieve.
+pub const DEFAULT_ACCELERATION: usize = 1;
+
+/// The highest compression level of `BlockCompressor::new_hc`; higher levels are treated like it.
+pub const MAX_HC_LEVEL: usize = 16;
+
+/// Provides the maximum size that compressing `src_size` bytes into a block may output, i.e. how
+/// big the destination buffer has to be to be sure compression succeeds. Returns 0 if `src_size`
+/// is larger than `MAX_INPUT_SIZE`.
+pub fn compress_bound(src_size: usize) -> usize {
+    if src_size > MAX_INPUT_SIZE {
+        0
+    } else {
+        src_size + (src_size / 255) + 16
+    }
+}
+
+/// Compresses `src` into `dst` as a single block. `acceleration` trades compression ratio for
+/// speed; each increment above `DEFAULT_ACCELERATION` makes compression a few percent faster.
+/// `dst` has to be large enough to hold the compressed block, which it always is if it holds
+/// `compress_bound(src.len())` bytes.
+/// The result is either the number of bytes written into `dst` or an `Lz4Error` if the block didn't
+/// fit.
+#[cfg(not(feature = "pure-rust"))]
+pub fn compress(src: &[u8], dst: &mut [u8], acceleration: usize) -> Lz4Result<usize> {
+    if src.len() > MAX_INPUT_SIZE {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    let dst_len: usize = min(dst.len(), i32::MAX as usize);
+    let acceleration: usize = if acceleration < 1 { 1 } else { acceleration };
+    let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+    let dst_ptr: *mut c_char = dst.as_mut_ptr() as *mut c_char;
+    let result: c_int = unsafe {
+        LZ4_compress_fast(src_ptr,
+                          dst_ptr,
+                          src.len() as c_int,
+                          dst_len as c_int,
+                          acceleration as c_int)
+    };
+    if result <= 0 {
+        Err(Lz4Error { desc: "destination too small to hold compressed block".to_string() })
+    } else {
+        Ok(result as usize)
+    }
+}
+
+/// Compresses `src` into `dst` as a single block. `acceleration` trades compression ratio for
+/// speed; each increment above `DEFAULT_ACCELERATION` makes compression a few percent faster.
+/// `dst` has to be large enough to hold the compressed block, which it always is if it holds
+/// `compress_bound(src.len())` bytes.
+/// The result is either the number of bytes written into `dst` or an `Lz4Error` if the block didn't
+/// fit.
+#[cfg(feature = "pure-rust")]
+pub fn compress(src: &[u8], dst: &mut [u8], acceleration: usize) -> Lz4Result<usize> {
+    rust::compress(src, dst, acceleration)
+}
+
+/// Compresses as much of `src` as fits into `dst` as a single block, filling `dst` as far as
+/// possible. This packs data into a fixed output budget, e.g. a datagram, without having to guess
+/// how much input will fit.
+/// The result is either a tuple of the number of bytes consumed from `src` and the number of bytes
+/// written into `dst`, or an `Lz4Error` if compression failed, e.g. because `dst` is empty.
+#[cfg(not(feature = "pure-rust"))]
+pub fn compress_to_fit(src: &[u8], dst: &mut [u8]) -> Lz4Result<(usize, usize)> {
+    let src_len: usize = min(src.len(), MAX_INPUT_SIZE);
+    let dst_len: usize = min(dst.len(), i32::MAX as usize);
+    let mut src_size: c_int = src_len as c_int;
+    let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+    let dst_ptr: *mut c_char = dst.as_mut_ptr() as *mut c_char;
+    let result: c_int = unsafe {
+        LZ4_compress_destSize(src_ptr, dst_ptr, &mut src_size, dst_len as c_int)
+    };
+    if result <= 0 {
+        Err(Lz4Error { desc: "destination too small to hold compressed block".to_string() })
+    } else {
+        Ok((src_size as usize, result as usize))
+    }
+}
+
+/// Compresses as much of `src` as fits into `dst` as a single block, filling `dst` as far as
+/// possible. This packs data into a fixed output budget, e.g. a datagram, without having to guess
+/// how much input will fit.
+/// The result is either a tuple of the number of bytes consumed from `src` and the number of bytes
+/// written into `dst`, or an `Lz4Error` if compression failed, e.g. because `dst` is empty.
+#[cfg(feature = "pure-rust")]
+pub fn compress_to_fit(src: &[u8], dst: &mut [u8]) -> Lz4Result<(usize, usize)> {
+    rust::compress_to_fit(src, dst)
+}
+
+/// Compresses independent blocks one after another with the same compression state, so that
+/// compressing many small blocks doesn't allocate a state for each of them. Each block decodes on
+/// its own with `decompress`.
+#[cfg(not(feature = "pure-rust"))]
+pub struct BlockCompressor {
+    state: Vec<u64>,
+    level: Level,
+}
+
+/// How a `BlockCompressor` compresses its blocks.
+#[cfg(not(feature = "pure-rust"))]
+#[derive(Copy, Clone)]
+enum Level {
+    /// `LZ4_compress_fast_extState` with this acceleration.
+    Fast(usize),
+    /// `LZ4_compress_HC_extStateHC` with this compression level.
+    High(usize),
+}
+
+#[cfg(not(feature = "pure-rust"))]
+impl BlockCompressor {
+    /// Creates a `BlockCompressor` that compresses like `compress` with `acceleration`.
+    pub fn new(acceleration: usize) -> BlockCompressor {
+        let size: c_int = unsafe { LZ4_sizeofState() };
+        let acceleration: usize = if acceleration < 1 { 1 } else { acceleration };
+        BlockCompressor::with_state_size(size as usize, Level::Fast(acceleration))
+    }
+
+    /// Creates a `BlockCompressor` that compresses with LZ4 HC at compression `level`, up to
+    /// `MAX_HC_LEVEL`; 0 selects the default level. This never fails here, but with the `pure-rust`
+    /// feature, which has no LZ4 HC, it always does.
+    pub fn new_hc(level: usize) -> Lz4Result<BlockCompressor> {
+        let size: c_int = unsafe { LZ4_sizeofStateHC() };
+        let level: Level = Level::High(min(level, MAX_HC_LEVEL));
+        Ok(BlockCompressor::with_state_size(size as usize, level))
+    }
+
+    /// Allocates a state of `size` bytes, aligned on 8 bytes as lz4 requires.
+    fn with_state_size(size: usize, level: Level) -> BlockCompressor {
+        BlockCompressor {
+            state: repeat(0u64).take((size + 7) / 8).collect(),
+            level: level,
+        }
+    }
+
+    /// Compresses `src` into `dst` as a single block. `dst` has to be large enough to hold the
+    /// compressed block, which it always is if it holds `compress_bound(src.len())` bytes.
+    /// The result is either the number of bytes written into `dst` or an `Lz4Error` if the block
+    /// didn't fit.
+    pub fn compress(&mut self, src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+        if src.len() > MAX_INPUT_SIZE {
+            return Err(Lz4Error { desc: "block too large".to_string() });
+        }
+        let dst_len: usize = min(dst.len(), i32::MAX as usize);
+        let state_ptr: *mut c_void = self.state.as_mut_ptr() as *mut c_void;
+        let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+        let dst_ptr: *mut c_char = dst.as_mut_ptr() as *mut c_char;
+        let result: c_int = unsafe {
+            match self.level {
+                Level::Fast(acceleration) => {
+                    LZ4_compress_fast_extState(state_ptr,
+                                               src_ptr,
+                                               dst_ptr,
+                                               src.len() as c_int,
+                                               dst_len as c_int,
+                                               acceleration as c_int)
+                },
+                Level::High(level) => {
+                    LZ4_compress_HC_extStateHC(state_ptr,
+                                               src_ptr,
+                                               dst_ptr,
+                                               src.len() as c_int,
+                                               dst_len as c_int,
+                                               level as c_int)
+                },
+            }
+        };
+        if result <= 0 {
+            Err(Lz4Error { desc: "destination too small to hold compressed block".to_string() })
+        } else {
+            Ok(result as usize)
+        }
+    }
+}
+
+/// Decompresses the block in `src` into `dst`, which has to be large enough to hold all of the
+/// decompressed data. The result is either the number of bytes written into `dst` or an `Lz4Error`
+/// if `src` is malformed or `dst` is too small.
+#[cfg(not(feature = "pure-rust"))]
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+    if src.len() > MAX_INPUT_SIZE || dst.len() > (i32::MAX as usize) {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+    let dst_ptr: *mut c_char = dst.as_mut_ptr() as *mut c_char;
+    let result: c_int = unsafe {
+        LZ4_decompress_safe(src_ptr, dst_ptr, src.len() as c_int, dst.len() as c_int)
+    };
+    if result < 0 {
+        Err(Lz4Error { desc: format!("malformed block at byte {}", -(result + 1)) })
+    } else {
+        Ok(result as usize)
+    }
+}
+
+/// Decompresses the block in `src` into `dst`, which has to be large enough to hold all of the
+/// decompressed data. The result is either the number of bytes written into `dst` or an `Lz4Error`
+/// if `src` is malformed or `dst` is too small.
+#[cfg(feature = "pure-rust")]
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+    rust::decompress(src, dst)
+}
+
+
+/// Decompresses only the first `target_len` bytes of the block in `src` into `dst`, skipping the
+/// work of decoding the rest of the block. `dst` has to hold at least `target_len` bytes, and may
+/// be written past `target_len` by the C implementation. Before lz4 1.9, that fails unless `dst`
+/// also has room for the rest of the literal run or match reaching past `target_len`, which is
+/// only certain if it can hold the whole block.
+/// The result is either the number of bytes decompressed, which is less than `target_len` only if
+/// the whole block is shorter, or an `Lz4Error` if `src` is malformed.
+#[cfg(not(feature = "pure-rust"))]
+pub fn decompress_partial(src: &[u8], dst: &mut [u8], target_len: usize) -> Lz4Result<usize> {
+    if src.len() > MAX_INPUT_SIZE || dst.len() > (i32::MAX as usize) {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    let target_len: usize = min(target_len, dst.len());
+    let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+    let dst_ptr: *mut c_char = dst.as_mut_ptr() as *mut c_char;
+    let result: c_int = unsafe {
+        LZ4_decompress_safe_partial(src_ptr,
+                                    dst_ptr,
+                                    src.len() as c_int,
+                                    target_len as c_int,
+                                    dst.len() as c_int)
+    };
+    if result < 0 {
+        Err(Lz4Error { desc: format!("malformed block at byte {}", -(result + 1)) })
+    } else {
+        Ok(min(result as usize, target_len))
+    }
+}
+
+/// Decompresses only the first `target_len` bytes of the block in `src` into `dst`, skipping the
+/// work of decoding the rest of the block. `dst` has to hold at least `target_len` bytes.
+/// The result is either the number of bytes decompressed, which is less than `target_len` only if
+/// the whole block is shorter, or an `Lz4Error` if `src` is malformed.
+#[cfg(feature = "pure-rust")]
+pub fn decompress_partial(src: &[u8], dst: &mut [u8], target_len: usize) -> Lz4Result<usize> {
+    rust::decompress_partial(src, dst, target_len)
+}
+
+/// Decompresses the block in `src` into `dst`, starting at `prefix_len`. Matches may reach back
+/// into the first `prefix_len` bytes of `dst`, which have to hold the data that came right before
+/// the block, e.g. the previous blocks of a frame with linked blocks.
+/// The result is either the number of bytes written after the prefix or an `Lz4Error` if `src`
+/// is malformed or `dst` is too small.
+#[cfg(not(feature = "pure-rust"))]
+pub fn decompress_with_prefix(src: &[u8], dst: &mut [u8], prefix_len: usize) -> Lz4Result<usize> {
+    if prefix_len > dst.len() {
+        return Err(Lz4Error { desc: "block prefix larger than destination".to_string() });
+    }
+    if src.len() > MAX_INPUT_SIZE || dst.len() > (i32::MAX as usize) {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    let src_ptr: *const c_char = src.as_ptr() as *const c_char;
+    let dict_ptr: *const c_char = dst.as_ptr() as *const c_char;
+    let dst_ptr: *mut c_char = dst.slice_from_mut(prefix_len).as_mut_ptr() as *mut c_char;
+    let dst_len: usize = dst.len() - prefix_len;
+    let result: c_int = unsafe {
+        LZ4_decompress_safe_usingDict(src_ptr,
+                                      dst_ptr,
+                                      src.len() as c_int,
+                                      dst_len as c_int,
+                                      dict_ptr,
+                                      prefix_len as c_int)
+    };
+    if result < 0 {
+        Err(Lz4Error { desc: format!("malformed block at byte {}", -(result + 1)) })
+    } else {
+        Ok(result as usize)
+    }
+}
+
+/// Decompresses the block in `src` into `dst`, starting at `prefix_len`. Matches may reach back
+/// into the first `prefix_len` bytes of `dst`, which have to hold the data that came right before
+/// the block, e.g. the previous blocks of a frame with linked blocks.
+/// The result is either the number of bytes written after the prefix or an `Lz4Error` if `src`
+/// is malformed or `dst` is too small.
+#[cfg(feature = "pure-rust")]
+pub fn decompress_with_prefix(src: &[u8], dst: &mut [u8], prefix_len: usize) -> Lz4Result<usize> {
+    rust::decompress_with_prefix(src, dst, prefix_len)
+}
+
+/// Compresses `src` into a new block with its uncompressed size prepended as 4 little endian
+/// bytes, the format `lz4.block.compress` in python-lz4 writes by default and lz4-java's
+/// `LZ4CompressorWithLength` writes, too. `acceleration` works like in `compress`.
+/// The result is either the size prefix followed by the block or an `Lz4Error` if `src` is larger
+/// than `MAX_INPUT_SIZE`.
+pub fn compress_prepend_size(src: &[u8], acceleration: usize) -> Lz4Result<Vec<u8>> {
+    if src.len() > MAX_INPUT_SIZE {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    let bound: usize = compress_bound(src.len());
+    let mut dst: Vec<u8> = repeat(0u8).take(SIZE_PREFIX_LEN + bound).collect();
+    let size: usize = src.len();
+    for (i, b) in dst.iter_mut().take(SIZE_PREFIX_LEN).enumerate() {
+        *b = (size >> (8 * i)) as u8;
+    }
+    let len: usize = try!(compress(src, dst.slice_from_mut(SIZE_PREFIX_LEN), acceleration));
+    dst.truncate(SIZE_PREFIX_LEN + len);
+    Ok(dst)
+}
+
+/// Decompresses a block whose uncompressed size is prepended as 4 little endian bytes, as written
+/// by `compress_prepend_size`. Since the size comes from the input, a declared size larger than
+/// `max_size` is rejected before anything is allocated, so that a corrupted or malicious prefix
+/// can't make this allocate up to 4GB.
+/// The result is either the decompressed data or an `Lz4Error` if the prefix is missing, exceeds
+/// `max_size`, or doesn't match the size of the decompressed block, or if the block is malformed.
+pub fn decompress_size_prepended(src: &[u8], max_size: usize) -> Lz4Result<Vec<u8>> {
+    if src.len() < SIZE_PREFIX_LEN {
+        return Err(Lz4Error { desc: "block too short to hold its size".to_string() });
+    }
+    let mut size: usize = 0;
+    for (i, b) in src.iter().take(SIZE_PREFIX_LEN).enumerate() {
+        size |= (*b as usize) << (8 * i);
+    }
+    if size > max_size {
+        return Err(Lz4Error {
+            desc: format!("declared block size {} exceeds maximum of {}", size, max_size)
+        });
+    }
+    let mut dst: Vec<u8> = repeat(0u8).take(size).collect();
+    let len: usize = try!(decompress(src.slice_from(SIZE_PREFIX_LEN), dst.as_mut_slice()));
+    if len != size {
+        return Err(Lz4Error {
+            desc: format!("block decompressed to {} bytes, but declared {}", len, size)
+        });
+    }
+    Ok(dst)
+}
+
+/// Simple tests that blocks are compressed and decoded the same way by either backend. See the
+/// crate docs for how they run under Miri.
+#[cfg(any(feature = "pure-rust", not(miri)))]
+mod block_tests {
+    use super::{compress, compress_bound, compress_prepend_size, compress_to_fit, decompress,
+                decompress_partial, decompress_size_prepended, decompress_with_prefix,
+                BlockCompressor, DEFAULT_ACCELERATION};
+    #[cfg(not(feature = "pure-rust"))]
+    use super::rust;
+    #[cfg(not(feature = "pure-rust"))]
+    use std::iter::repeat;
+
+    /// "0123456789" five times over: ten literals, a 35 byte match at offset 10 and the last five
+    /// bytes as literals.
+    const BLOCK: &'static [u8] = b"\xaf0123456789\x0a\x00\x10\x5056789";
+
+    #[test]
+    fn decompresses_block() {
+        let mut buf: [u8; 64] = [0; 64];
+        let len: usize = decompress(BLOCK, &mut buf).ok().unwrap();
+        assert_eq!(b"01234567890123456789012345678901234567890123456789", buf.slice_to(len));
+    }
+
+    /// Tests that partial decompression stops at the target length, or at the end of the block
+    /// if that comes first.
+    #[test]
+    fn decompresses_partial() {
+        let mut buf: [u8; 64] = [0; 64];
+        let len: usize = decompress_partial(BLOCK, &mut buf, 12).ok().unwrap();
+        assert_eq!(b"012345678901", buf.slice_to(len));
+
+        let len: usize = decompress_partial(BLOCK, &mut buf, 64).ok().unwrap();
+        assert_eq!(b"01234567890123456789012345678901234567890123456789", buf.slice_to(len));
+    }
+
+    #[test]
+    fn rejects_small_dst() {
+        let mut buf: [u8; 40] = [0; 40];
+        assert!(decompress(BLOCK, &mut buf).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_offset() {
+        let block: &[u8] = b"\xaf0123456789\x0b\x00\x10\x5056789";
+        let mut buf: [u8; 64] = [0; 64];
+        assert!(decompress(block, &mut buf).is_err());
+    }
+
+    /// Tests that compressed blocks decompress to the original data, with and without
+    /// acceleration.
+    #[test]
+    fn round_trip() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        for acceleration in [DEFAULT_ACCELERATION, 8].iter() {
+            let mut compressed: [u8; 128] = [0; 128];
+            assert!(compress_bound(data.len()) <= compressed.len());
+            let len: usize = compress(data, &mut compressed, *acceleration).ok().unwrap();
+
+            let mut buf: [u8; 128] = [0; 128];
+            let bytes: usize = decompress(compressed.slice_to(len), &mut buf).ok().unwrap();
+            assert_eq!(data, buf.slice_to(bytes));
+        }
+    }
+
+    /// Tests that a `BlockCompressor` keeps compressing blocks that decode on their own, also
+    /// when a block follows a longer one with different content.
+    #[test]
+    fn block_compressor_reuses_state() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut compressors: Vec<BlockCompressor> =
+            vec![BlockCompressor::new(DEFAULT_ACCELERATION)];
+        match BlockCompressor::new_hc(9) {
+            // there is no Rust implementation of LZ4 HC
+            Err(_) if cfg!(feature = "pure-rust") => {},
+            result => { compressors.push(result.ok().unwrap()); },
+        }
+        for compressor in compressors.iter_mut() {
+            for &(start, end) in [(0, data.len()), (30, data.len()), (5, 25), (0, 0)].iter() {
+                let src: &[u8] = data.slice(start, end);
+                let mut compressed: [u8; 128] = [0; 128];
+                let len: usize = compressor.compress(src, &mut compressed).ok().unwrap();
+
+                let mut buf: [u8; 128] = [0; 128];
+                let bytes: usize = decompress(compressed.slice_to(len), &mut buf).ok().unwrap();
+                assert_eq!(src, buf.slice_to(bytes));
+            }
+            let mut small: [u8; 8] = [0; 8];
+            assert!(compressor.compress(data, &mut small).is_err());
+        }
+    }
+
+    /// Tests that `compress_to_fit` fills a small destination with a block that decompresses to
+    /// the start of the input, and compresses everything when there is room.
+    #[test]
+    fn compresses_to_fit() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut compressed: [u8; 20] = [0; 20];
+        let (consumed, len) = compress_to_fit(data, &mut compressed).ok().unwrap();
+        assert!(consumed > 0 && consumed < data.len());
+        assert!(len <= compressed.len());
+
+        let mut buf: [u8; 128] = [0; 128];
+        let bytes: usize = decompress(compressed.slice_to(len), &mut buf).ok().unwrap();
+        assert_eq!(data.slice_to(consumed), buf.slice_to(bytes));
+
+        let mut compressed: [u8; 128] = [0; 128];
+        let (consumed, _) = compress_to_fit(data, &mut compressed).ok().unwrap();
+        assert_eq!(consumed, data.len());
+    }
+
+    /// Tests size prepended blocks against `BLOCK` with its size prepended the way python-lz4 lays
+    /// them out, and that declared sizes above the maximum or differing from the block are
+    /// rejected.
+    #[test]
+    fn size_prepended() {
+        let mut block: Vec<u8> = vec![50, 0, 0, 0];
+        block.push_all(BLOCK);
+        let data: Vec<u8> = decompress_size_prepended(block.as_slice(), 1024).ok().unwrap();
+        assert_eq!(b"01234567890123456789012345678901234567890123456789", data.as_slice());
+        assert!(decompress_size_prepended(block.as_slice(), 49).is_err());
+        block[0] = 51;
+        assert!(decompress_size_prepended(block.as_slice(), 1024).is_err());
+        assert!(decompress_size_prepended(b"\x32\x00", 1024).is_err());
+
+        let compressed: Vec<u8> = compress_prepend_size(data.as_slice(), DEFAULT_ACCELERATION)
+            .ok().unwrap();
+        assert_eq!(b"\x32\x00\x00\x00", compressed.slice_to(4));
+        let round_trip: Vec<u8> = decompress_size_prepended(compressed.as_slice(), 50)
+            .ok().unwrap();
+        assert_eq!(data, round_trip);
+    }
+
+    /// Tests that blocks compressed by the C library decode with the Rust implementation, and the
+    /// other way around, in full and partially.
+    #[cfg(not(feature = "pure-rust"))]
+    #[test]
+    fn backends_are_compatible() {
+        let mut data: Vec<u8> = Vec::new();
+        for i in 0..500 {
+            data.push_all(b"This is a test\nA what?\n");
+            data.push(b'a' + (i % 26) as u8);
+        }
+        let mut c_block: Vec<u8> = repeat(0u8).take(compress_bound(data.len())).collect();
+        let c_len: usize = compress(data.as_slice(), c_block.as_mut_slice(), DEFAULT_ACCELERATION)
+            .ok().unwrap();
+        let mut rust_block: Vec<u8> = repeat(0u8).take(compress_bound(data.len())).collect();
+        let rust_len: usize = rust::compress(data.as_slice(), rust_block.as_mut_slice(),
+                                             DEFAULT_ACCELERATION).ok().unwrap();
+
+        let mut buf: Vec<u8> = repeat(0u8).take(data.len()).collect();
+        let len: usize = rust::decompress(c_block.slice_to(c_len), buf.as_mut_slice())
+            .ok().unwrap();
+        assert_eq!(data.as_slice(), buf.slice_to(len));
+        let len: usize = decompress(rust_block.slice_to(rust_len), buf.as_mut_slice())
+            .ok().unwrap();
+        assert_eq!(data.as_slice(), buf.slice_to(len));
+
+        let len: usize = rust::decompress_partial(c_block.slice_to(c_len), buf.as_mut_slice(),
+                                                  100).ok().unwrap();
+        assert_eq!(data.slice_to(100), buf.slice_to(len));
+        let len: usize = decompress_partial(rust_block.slice_to(rust_len), buf.as_mut_slice(),
+                                            100).ok().unwrap();
+        assert_eq!(data.slice_to(100), buf.slice_to(len));
+    }
+
+    /// Tests that matches may refer back into the prefix.
+    #[test]
+    fn decompresses_with_prefix() {
+        // a 10 byte match at offset 10, reaching back into the prefix, then five literals
+        let block: &[u8] = b"\x06\x0a\x00\x5056789";
+        let mut buf: [u8; 64] = [0; 64];
+        for (i, b) in b"0123456789".iter().enumerate() {
+            buf[i] = *b;
+        }
+        let len: usize = decompress_with_prefix(block, &mut buf, 10).ok().unwrap();
+        assert_eq!(b"012345678956789", buf.slice(10, 10 + len));
+    }
+}
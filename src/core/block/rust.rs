@@ -0,0 +1,88 @@
+//! The safe Rust implementation of the block functions, which `core::block` uses when the
+//! `pure-rust` feature is enabled. It is built either way, so that blocks compressed by the C
+//! library can be checked against it, and the other way around. Each function works like its
+//! counterpart in `core::block`.
+
+#![forbid(unsafe_code)]
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::cmp::min;
+
+use super::super::super::frame::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+use super::MAX_INPUT_SIZE;
+use super::encoder;
+use super::decoder;
+
+/// Compresses `src` into `dst` as a single block, like `core::block::compress`.
+pub fn compress(src: &[u8], dst: &mut [u8], acceleration: usize) -> Lz4Result<usize> {
+    if src.len() > MAX_INPUT_SIZE {
+        return Err(Lz4Error { desc: "block too large".to_string() });
+    }
+    encoder::compress(src, dst, acceleration)
+}
+
+/// Compresses as much of `src` as fits into `dst` as a single block, like
+/// `core::block::compress_to_fit`.
+pub fn compress_to_fit(src: &[u8], dst: &mut [u8]) -> Lz4Result<(usize, usize)> {
+    let src_len: usize = min(src.len(), MAX_INPUT_SIZE);
+    encoder::compress_to_fit(src.slice_to(src_len), dst)
+}
+
+/// Decompresses the block in `src` into `dst`, like `core::block::decompress`.
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+    decoder::decompress(src, dst)
+}
+
+/// Decompresses only the first `target_len` bytes of the block in `src` into `dst`, like
+/// `core::block::decompress_partial`. Nothing past `target_len` is written.
+pub fn decompress_partial(src: &[u8], dst: &mut [u8], target_len: usize) -> Lz4Result<usize> {
+    let target_len: usize = min(target_len, dst.len());
+    decoder::decompress_partial(src, dst.slice_to_mut(target_len))
+}
+
+/// Decompresses the block in `src` into `dst` after the first `prefix_len` bytes, which matches
+/// may refer back into, like `core::block::decompress_with_prefix`.
+pub fn decompress_with_prefix(src: &[u8], dst: &mut [u8], prefix_len: usize) -> Lz4Result<usize> {
+    decoder::decompress_with_prefix(src, dst, prefix_len)
+}
+
+/// Compresses independent blocks one after another with the same hash table, so that compressing
+/// many small blocks neither allocates nor clears a table for each of them. Each block decodes on
+/// its own with `decompress`.
+pub struct BlockCompressor {
+    table: encoder::HashTable,
+    acceleration: usize,
+}
+
+impl BlockCompressor {
+    /// Creates a `BlockCompressor` that compresses like `compress` with `acceleration`.
+    pub fn new(acceleration: usize) -> BlockCompressor {
+        BlockCompressor {
+            table: encoder::HashTable::new(),
+            acceleration: acceleration,
+        }
+    }
+
+    /// Would create a `BlockCompressor` for LZ4 HC, but there is no Rust implementation of LZ4 HC,
+    /// so this always returns an error instead of quietly compressing blocks some other way.
+    pub fn new_hc(_level: usize) -> Lz4Result<BlockCompressor> {
+        Err(Lz4Error { desc: "LZ4 HC needs the C library".to_string() })
+    }
+
+    /// Compresses `src` into `dst` as a single block. `dst` has to be large enough to hold the
+    /// compressed block, which it always is if it holds `compress_bound(src.len())` bytes.
+    /// The result is either the number of bytes written into `dst` or an `Lz4Error` if the block
+    /// didn't fit.
+    pub fn compress(&mut self, src: &[u8], dst: &mut [u8]) -> Lz4Result<usize> {
+        if src.len() > MAX_INPUT_SIZE {
+            return Err(Lz4Error { desc: "block too large".to_string() });
+        }
+        self.table.compress(src, dst, self.acceleration)
+    }
+}
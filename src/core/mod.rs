@@ -1,12 +1,25 @@
 //! This module contains wrappers around the functions inside `lz4.h`
 
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
 pub use self::structs::Lz4Version;
 
-use super::liblz4::core::LZ4_versionNumber;
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::LZ4_versionNumber;
 
 pub mod structs;
+pub mod block;
 
+#[cfg(not(feature = "pure-rust"))]
 pub fn version() -> Lz4Version {
     let result = unsafe { LZ4_versionNumber() };
     Lz4Version::new(result as usize)
 }
+
+/// With the `pure-rust` feature no liblz4 is linked, so there is no library version to report and
+/// this is always 0.0.0.
+#[cfg(feature = "pure-rust")]
+pub fn version() -> Lz4Version {
+    Lz4Version::new(0)
+}
@@ -1,5 +1,8 @@
 //! This module contains structs and trait implementations needed for the core module.
 
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
 use std::fmt;
 
 const MAJOR: usize = 100*100;
@@ -2,23 +2,47 @@
 //! data written to it into an LZ4 Frame. 
 //! Additionally, safe wrappers for all of the public compression functions from `lz4frame.h`
 //! are provided
+//! The `Compressor` and `compress_file` need the `std` feature; the slice-based functions don't.
 
 extern crate libc;
 extern crate collections;
 
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+#[cfg(not(feature = "pure-rust"))]
 use libc::{size_t, c_void};
 
+#[cfg(feature = "std")]
 use std::io::fs::File;
+#[cfg(feature = "std")]
 use std::io::{IoResult, IoError, IoErrorKind};
 use std::default::Default;
+#[cfg(feature = "std")]
 use std::cmp::min;
-
-use super::super::liblz4::frame::types::{
+#[cfg(feature = "std")]
+use std::iter::repeat;
+#[cfg(feature = "std")]
+use std::slice::bytes::copy_memory;
+#[cfg(not(feature = "pure-rust"))]
+use std::ptr;
+
+use lz4rs_sys::{
     FramePreferences,
     FrameCompressOptions,
 };
 
-use super::super::liblz4::frame::{
+#[cfg(feature = "std")]
+use lz4rs_sys::BlockMode;
+
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::Context;
+
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::{
+    LZ4F_VERSION,
+    LZ4F_createCompressionContext,
+    LZ4F_freeCompressionContext,
     LZ4F_compressFrameBound,
     LZ4F_compressFrame,
     LZ4F_compressBegin,
@@ -28,123 +52,209 @@ use super::super::liblz4::frame::{
     LZ4F_compressEnd,
 };
 
-use super::structs::{
-    Lz4Result,
-    FrameContextType,
-    Lz4FrameContext,
-};
+use super::structs::Lz4Result;
+#[cfg(feature = "std")]
+use super::structs::{CompressOptions, context_in_frame};
+
+#[cfg(any(feature = "std", not(feature = "pure-rust")))]
+use super::structs::Lz4Error;
 
+#[cfg(not(feature = "pure-rust"))]
 use super::{
     maybe_error,
 };
 
-/// How big the internal buffer in `Compressor` should be by default.
-const DEFAULT_BUF_SIZE: usize = 1024;
+#[cfg(feature = "std")]
+use super::header::rewrite_header_checksum;
+#[cfg(not(feature = "pure-rust"))]
+use super::header::dictionary_not_given;
 
+#[cfg(feature = "pure-rust")]
+use super::encoder;
+#[cfg(feature = "pure-rust")]
+use super::encoder::FrameEncoder;
+
+#[cfg(all(feature = "mmap", unix))]
+use collections::slice;
+#[cfg(all(feature = "mmap", unix))]
+use std::os::MemoryMap;
+#[cfg(all(feature = "mmap", unix))]
+use super::map_file;
+
+#[cfg(feature = "std")]
 pub struct Compressor<W> {
     inner: W,
-    cctx: Lz4FrameContext,
+    cctx: CompressionContext,
     buffer: Vec<u8>,
-    buf_size: usize,
-    opts: FrameCompressOptions,
+    chunk_size: usize,
+    opts: CompressOptions,
     prefs: FramePreferences,
+    total_in: u64,
 }
 
+#[cfg(feature = "std")]
 impl<W: Writer> Compressor<W> {
     /// Creates a new `Compressor` wrapping the given `Writer` `dst`. If any of `prefs`, `buf_size`,
     /// or `opts` is provided, the `Compressor` is created with those options configured. Otherwise,
     /// their defaults are used.
-    pub fn new(mut dst: W,
+    /// `buf_size` is the largest number of bytes handed to `compress_update` at once. It defaults
+    /// to the block size in `prefs`, so that every call can compress a whole block straight from
+    /// the written data; the internal buffer is sized to hold the compressed result.
+    /// With `legacy_header_checksum` set in `opts`, the frame header is written with the checksum
+    /// Kafka computed before message format v1.
+    pub fn new(dst: W,
                prefs: Option<FramePreferences>,
                buf_size: Option<usize>,
-               opts: Option<FrameCompressOptions>) -> Lz4Result<Compressor<W>> {
-        let mut ctx: Lz4FrameContext = try!(create_compression_context());
+               opts: Option<CompressOptions>) -> Lz4Result<Compressor<W>> {
+        let ctx: CompressionContext = try!(create_compression_context());
+        Compressor::with_context(dst, ctx, prefs, buf_size, opts)
+    }
 
+    /// Creates a `Compressor` like `new` that compresses with `ctx` instead of creating a context
+    /// of its own, e.g. one taken from a `ContextPool`. Fails if `ctx` is in the middle of a
+    /// frame. `finish` hands it back once the frame is ended.
+    pub fn with_context(mut dst: W,
+                        mut ctx: CompressionContext,
+                        prefs: Option<FramePreferences>,
+                        buf_size: Option<usize>,
+                        opts: Option<CompressOptions>) -> Lz4Result<Compressor<W>> {
+        if ctx.in_frame() {
+            return Err(context_in_frame());
+        }
         let prefs: FramePreferences = prefs.unwrap_or_else(Default::default);
-        let opts: FrameCompressOptions = opts.unwrap_or_else(Default::default);
-
-        let s: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
-        let size: usize = compress_bound(s, Some(&prefs));
-        let mut buf: Vec<u8> = Vec::with_capacity(size);
+        let mut opts: CompressOptions = opts.unwrap_or_else(Default::default);
+        // the written data is gone after each call, so lz4frame has to keep its own copy
+        opts.frame = Default::default();
 
         // add frame header
-        let len: usize = try!(compress_begin(&mut ctx, buf.as_mut_slice(), size, Some(&prefs)));
-        unsafe { buf.set_len(len); }
-        dst.write(buf.as_slice());
+        let (buf, chunk_size, len) = try!(begin_frame(&mut ctx, &prefs, buf_size, &opts));
+        dst.write(buf.slice_to(len));
         Ok(Compressor {
             inner: dst,
             cctx: ctx,
             buffer: buf,
-            buf_size: size,
+            chunk_size: chunk_size,
             opts: opts,
             prefs: prefs,
+            total_in: 0,
         })
     }
 
-    /// Creates a `Compressor` with all default options and preferences set.
-    pub fn default(mut dst: W) -> Lz4Result<Compressor<W>> {
-        let mut ctx: Lz4FrameContext = try!(create_compression_context());
+    /// Creates a `Compressor` like `new` whose frame declares `content_size` bytes of content in
+    /// its header, so that readers know the size before decompressing. Writing more than
+    /// `content_size` bytes fails, and so does ending the frame after fewer.
+    pub fn with_content_size(dst: W,
+                             content_size: u64,
+                             prefs: Option<FramePreferences>,
+                             opts: Option<CompressOptions>) -> Lz4Result<Compressor<W>> {
+        let mut prefs: FramePreferences = prefs.unwrap_or_else(Default::default);
+        prefs.frame_info.content_size = content_size;
+        Compressor::new(dst, Some(prefs), None, opts)
+    }
 
-        let prefs: FramePreferences = Default::default();
-        let opts: FrameCompressOptions = Default::default();
-        let size: usize = compress_bound(DEFAULT_BUF_SIZE, Some(&prefs));
-        let mut buf: Vec<u8> = Vec::with_capacity(size);
+    /// Counts `len` more bytes of input, failing if that exceeds the declared content size.
+    fn count_input(&mut self, len: usize) -> IoResult<()> {
+        count_input(&self.prefs, &mut self.total_in, len).map_err(|e| compress_error(e.desc))
+    }
 
-        let len: usize = try!(compress_begin(&mut ctx, buf.as_mut_slice(), size, Some(&prefs)));
-        unsafe {buf.set_len(len); }
-        dst.write(buf.as_slice());
-        Ok(Compressor {
-            inner: dst,
-            cctx: ctx,
-            buffer: buf,
-            buf_size: size,
-            opts: opts,
-            prefs: prefs,
-        })
+    /// Creates a `Compressor` with all default options and preferences set.
+    pub fn default(dst: W) -> Lz4Result<Compressor<W>> {
+        Compressor::new(dst, None, None, None)
     }
 
     /// Unwraps this `Compressor`, returning underlying Writer
     fn into_inner(self) -> W { self.inner }
 
+    /// Compresses each slice in `slices` in order, exactly as if they had been passed to `write`
+    /// one after another, and returns the number of compressed bytes written to the inner
+    /// `Writer`. Like `write`, it hands each slice to lz4frame in pieces of at most `buf_size`
+    /// bytes, so the internal buffer keeps its size.
+    /// A piece is handed to lz4frame with `stable_src` set, so that its blocks are compressed
+    /// straight from it without being kept as the dictionary, whenever at least a block's worth of
+    /// data follows it. With linked blocks, lz4frame keeps pointing into the last piece it
+    /// compressed a block from with `stable_src` until it compresses the next block, so that next
+    /// block has to come before this call returns. The other pieces are compressed without
+    /// `stable_src`, which saves the dictionary inside the context, and none of the slices need to
+    /// outlive the call.
+    pub fn compress_slices(&mut self, slices: &[&[u8]]) -> IoResult<usize> {
+        // the slices outlive every block compressed with `stable_opts`, as described above
+        let stable_opts: FrameCompressOptions = unsafe { FrameCompressOptions::new(true) };
+        let copy_opts: FrameCompressOptions = Default::default();
+        let block_size: usize = self.prefs.frame_info.block_size_id.bytes();
+        let linked: bool = self.prefs.frame_info.block_mode == BlockMode::Linked;
+        let mut remaining: usize = slices.iter().fold(0, |sum, slice| sum + slice.len());
+        let mut written: usize = 0;
+
+        for slice in slices.iter() {
+            try!(self.count_input(slice.len()));
+            for piece in slice.chunks(self.chunk_size) {
+                remaining -= piece.len();
+                let opts: &FrameCompressOptions = if !linked || remaining >= block_size {
+                    &stable_opts
+                } else {
+                    &copy_opts
+                };
+                match compress_update(&mut self.cctx,
+                                      self.buffer.as_mut_slice(),
+                                      piece,
+                                      Some(opts)) {
+                    Ok(len) => {
+                        try!(self.inner.write(self.buffer.slice_to(len)));
+                        written += len;
+                    },
+                    Err(lz4err) => { return Err(compress_error(lz4err.desc)); },
+                }
+            }
+        }
+        Ok(written)
+    }
+
     /// Properly finishes the frame being compressed by calling `compress_end` and writing the
-    /// result to the inner `Writer`
+    /// result to the inner `Writer`. Fails if the frame declares a content size other than the
+    /// number of bytes written.
     pub fn end(&mut self) -> Lz4Result<usize> {
+        try!(check_content_size(&self.prefs, self.total_in));
         let len: usize = try!(compress_end(&mut self.cctx,
                                            self.buffer.as_mut_slice(),
-                                           self.buf_size,
-                                           Some(&self.opts)));
-        unsafe { self.buffer.set_len(len) }
-        self.inner.write(self.buffer.as_slice());
+                                           Some(&self.opts.frame)));
+        self.inner.write(self.buffer.slice_to(len));
         Ok(len)
     }
 
     /// finishes the frame being compressed and returns the inner `Writer` for further use.
-    pub fn done(mut self) -> (W, Lz4Result<usize>) {
+    pub fn done(self) -> (W, Lz4Result<usize>) {
+        let (inner, _, end_res) = self.finish();
+        (inner, end_res)
+    }
+
+    /// Finishes the frame being compressed like `done`, and also returns the compression context,
+    /// e.g. to hand it back to a `ContextPool`. If ending the frame failed, the context is still
+    /// in the middle of it and can't be used for another frame.
+    pub fn finish(mut self) -> (W, CompressionContext, Lz4Result<usize>) {
         let end_res: Lz4Result<usize> = self.end();
-        (self.into_inner(), end_res)
+        (self.inner, self.cctx, end_res)
     }
 }
 
+#[cfg(feature = "std")]
 impl<W: Writer> Writer for Compressor<W> {
     /// Implementation of `write` for `Compressor`. Whenever `write` is called, the given byte buf
     /// is compressed and written to the inner `Writer` inside `Compressor`. It uses
     /// `compress_update` to compress the bytes before writing.
     fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        try!(self.count_input(buf.len()));
 
         let mut buf_offset: usize = 0;
 
         while buf_offset < buf.len() {
             let rem_space: usize = buf.len() - buf_offset;
-            let size: usize = min(self.buf_size, rem_space);
+            let size: usize = min(self.chunk_size, rem_space);
             match compress_update(&mut self.cctx,
                                   self.buffer.as_mut_slice(),
-                                  self.buf_size,
-                                  buf.slice_from(buf_offset),
-                                  size,
-                                  Some(&self.opts)) {
+                                  buf.slice(buf_offset, buf_offset + size),
+                                  Some(&self.opts.frame)) {
                 Ok(len) => {
-                    unsafe { self.buffer.set_len(len); }
-                    try!(self.inner.write(self.buffer.as_slice()));
+                    try!(self.inner.write(self.buffer.slice_to(len)));
                     buf_offset += size;
                 },
                 Err(lz4err) => {
@@ -163,14 +273,10 @@ impl<W: Writer> Writer for Compressor<W> {
     /// `Compressor`'s compression context, writing it to the inner `Writer`.
     fn flush(&mut self) -> IoResult<()> {
         loop {
-            match flush(&mut self.cctx,
-                        self.buffer.as_mut_slice(),
-                        self.buf_size,
-                        Some(&self.opts)) {
+            match flush(&mut self.cctx, self.buffer.as_mut_slice(), Some(&self.opts.frame)) {
                 Ok(len) => {
                     if len == 0 { break; }
-                    unsafe { self.buffer.set_len(len); }
-                    self.inner.write(self.buffer.as_slice());
+                    self.inner.write(self.buffer.slice_to(len));
                 },
                 Err(lz4err) => {
                     return Err(IoError { 
@@ -185,11 +291,189 @@ impl<W: Writer> Writer for Compressor<W> {
     }
 }
 
+/// Wraps a `Reader` of uncompressed data, and reads as an LZ4 frame of that data, from the header
+/// to the end mark. Data is read from the inner `Reader` and compressed only as compressed bytes
+/// are asked for.
+#[cfg(feature = "std")]
+pub struct CompressingReader<R> {
+    inner: R,
+    cctx: CompressionContext,
+    src: Vec<u8>,
+    buffer: Vec<u8>,
+    buf_offset: usize,
+    buf_size: usize,
+    opts: CompressOptions,
+    prefs: FramePreferences,
+    total_in: u64,
+    ended: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Reader> CompressingReader<R> {
+    /// Creates a new `CompressingReader` compressing the data read from `src`. `prefs`, `buf_size`
+    /// and `opts` work as they do for `Compressor::new`: `buf_size` is the largest number of bytes
+    /// read from `src` and handed to `compress_update` at once, and defaults to the block size.
+    /// If `prefs` declare a content size, reading fails once `src` turns out to hold more or less
+    /// data than that.
+    pub fn new(src: R,
+               prefs: Option<FramePreferences>,
+               buf_size: Option<usize>,
+               opts: Option<CompressOptions>) -> Lz4Result<CompressingReader<R>> {
+        let mut ctx: CompressionContext = try!(create_compression_context());
+
+        let prefs: FramePreferences = prefs.unwrap_or_else(Default::default);
+        let mut opts: CompressOptions = opts.unwrap_or_else(Default::default);
+        // the source buffer is refilled for every read, so lz4frame has to keep its own copy
+        opts.frame = Default::default();
+
+        // the frame header is the first thing read
+        let (buf, chunk_size, len) = try!(begin_frame(&mut ctx, &prefs, buf_size, &opts));
+        Ok(CompressingReader {
+            inner: src,
+            cctx: ctx,
+            src: repeat(0u8).take(chunk_size).collect(),
+            buffer: buf,
+            buf_offset: 0,
+            buf_size: len,
+            opts: opts,
+            prefs: prefs,
+            total_in: 0,
+            ended: false,
+        })
+    }
+
+    /// Unwraps this `CompressingReader`, returning the inner `Reader`.
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Reads the next chunk of data from the inner `Reader` and compresses it into the internal
+    /// buffer, or ends the frame once the inner `Reader` reaches EoF. The compressed result can
+    /// be empty if lz4frame just buffered the data.
+    fn fill_buffer(&mut self) -> IoResult<()> {
+        self.buf_offset = 0;
+        self.buf_size = 0;
+        let res: Lz4Result<usize> = match self.inner.read(self.src.as_mut_slice()) {
+            Ok(n) => {
+                try!(count_input(&self.prefs, &mut self.total_in, n)
+                         .map_err(|e| compress_error(e.desc)));
+                compress_update(&mut self.cctx,
+                                self.buffer.as_mut_slice(),
+                                self.src.slice_to(n),
+                                Some(&self.opts.frame))
+            },
+            Err(ref e) if e.kind == IoErrorKind::EndOfFile => {
+                try!(check_content_size(&self.prefs, self.total_in)
+                         .map_err(|e| compress_error(e.desc)));
+                self.ended = true;
+                compress_end(&mut self.cctx, self.buffer.as_mut_slice(), Some(&self.opts.frame))
+            },
+            Err(e) => { return Err(e); },
+        };
+        match res {
+            Ok(len) => {
+                self.buf_size = len;
+                Ok(())
+            },
+            Err(lz4err) => Err(compress_error(lz4err.desc)),
+        }
+    }
+}
+
+/// Begins a frame with `ctx` for a `Compressor` or `CompressingReader`. Returns the buffer sized
+/// for the compressed result of `buf_size` bytes (by default, the block size in `prefs`), with the
+/// frame header at its start, the chunk size and the length of the header. With
+/// `legacy_header_checksum` set in `opts`, the header gets Kafka's legacy checksum.
+#[cfg(feature = "std")]
+fn begin_frame(ctx: &mut CompressionContext,
+               prefs: &FramePreferences,
+               buf_size: Option<usize>,
+               opts: &CompressOptions) -> Lz4Result<(Vec<u8>, usize, usize)> {
+    let chunk_size: usize = match buf_size {
+        Some(s) => s,
+        None => prefs.frame_info.block_size_id.bytes(),
+    };
+    let size: usize = compress_bound(chunk_size, Some(prefs));
+    let mut buf: Vec<u8> = repeat(0u8).take(size).collect();
+
+    let len: usize = try!(compress_begin(ctx, buf.as_mut_slice(), Some(prefs)));
+    if opts.legacy_header_checksum {
+        rewrite_header_checksum(buf.slice_to_mut(len), true);
+    }
+    Ok((buf, chunk_size, len))
+}
+
+/// Adds `len` bytes of input to `total_in`, failing if that exceeds the content size declared in
+/// `prefs`.
+#[cfg(feature = "std")]
+fn count_input(prefs: &FramePreferences, total_in: &mut u64, len: usize) -> Lz4Result<()> {
+    let declared: u64 = prefs.frame_info.content_size;
+    *total_in += len as u64;
+    if declared != 0 && *total_in > declared {
+        return Err(Lz4Error {
+            desc: format!("frame declared {} bytes of content, got at least {}",
+                          declared, *total_in),
+        });
+    }
+    Ok(())
+}
+
+/// Checks, once the input has ended, that `total_in` is the content size declared in `prefs`.
+#[cfg(feature = "std")]
+fn check_content_size(prefs: &FramePreferences, total_in: u64) -> Lz4Result<()> {
+    let declared: u64 = prefs.frame_info.content_size;
+    if declared != 0 && declared != total_in {
+        return Err(Lz4Error {
+            desc: format!("frame declared {} bytes of content, got {}", declared, total_in),
+        });
+    }
+    Ok(())
+}
+
+/// Wraps a description of a failed compression in an `IoError`.
+#[cfg(feature = "std")]
+fn compress_error(detail: String) -> IoError {
+    IoError {
+        kind: IoErrorKind::OtherIoError,
+        desc: "lz4 compress error",
+        detail: Some(detail),
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Reader> Reader for CompressingReader<R> {
+    /// Implementation of `read` for `CompressingReader`. Fills `buf` with the next compressed
+    /// bytes of the frame, reading and compressing more data from the inner `Reader` only when
+    /// everything compressed so far has been read. Returns an EOF IoError after the end mark.
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        while self.buf_offset >= self.buf_size {
+            if self.ended {
+                return Err(IoError {
+                    kind: IoErrorKind::EndOfFile,
+                    desc: "No more to compress",
+                    detail: None,
+                });
+            }
+            try!(self.fill_buffer());
+        }
+
+        let len: usize = min(buf.len(), self.buf_size - self.buf_offset);
+        copy_memory(buf.slice_to_mut(len),
+                    self.buffer.slice(self.buf_offset, self.buf_offset + len));
+        self.buf_offset += len;
+        Ok(len)
+    }
+}
+
+/// Compresses the file at `src` into a frame in the file at `dst`, reading `buf_size` bytes at a
+/// time. The size of `src` is recorded as the frame's content size, and compression fails if the
+/// file changes size while it is read.
+/// Returns the size of the compressed file or an IoError if something failed during compression.
+#[cfg(feature = "std")]
 pub fn compress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoResult<usize> {
     let mut src_file = try!(File::open(src));
+    let src_size: u64 = try!(src_file.stat()).size;
     let mut dst_file = try!(File::create(dst));
 
-    let mut compressor = match Compressor::new(dst_file, None, None, None) {
+    let mut compressor = match Compressor::with_content_size(dst_file, src_size, None, None) {
         Ok(c) => c,
         Err(lz4err) => {
             return Err(IoError {
@@ -199,9 +483,8 @@ pub fn compress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoResul
             });
         }
     };
-    let size: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
-    let mut buf: Vec<u8> = Vec::with_capacity(size);
-    unsafe { buf.set_len(size) };
+    let size: usize = buf_size.unwrap_or(compressor.chunk_size);
+    let mut buf: Vec<u8> = repeat(0u8).take(size).collect();
     loop {
         let bytes_read = match src_file.read(buf.as_mut_slice()) {
             Ok(n) => n,
@@ -210,7 +493,47 @@ pub fn compress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoResul
         };
         try!(compressor.write(buf.slice_to(bytes_read)));
     }
-    let (compressed, _) = compressor.done();
+    let (compressed, result) = compressor.done();
+    if let Err(lz4err) = result {
+        return Err(IoError {
+            kind: IoErrorKind::OtherIoError,
+            desc: "lz4 compress error",
+            detail: Some(lz4err.desc),
+        });
+    }
+    let fstat = try!(compressed.stat());
+    Ok(fstat.size as usize)
+}
+
+/// Compresses the file at `src` into the file at `dst` like `compress_file`, but maps `src` into
+/// memory and compresses it straight from the mapping with `compress_slices` instead of reading it
+/// through a buffer. Only a block's worth of compressed data is held in memory at a time. The size
+/// of `src` is recorded as the frame's content size.
+/// Returns the size of the compressed file or an IoError if something failed during compression.
+#[cfg(all(feature = "mmap", unix))]
+pub fn compress_file_mmap(src: &Path, dst: &Path) -> IoResult<usize> {
+    let src_file = try!(File::open(src));
+    let src_size: usize = try!(src_file.stat()).size as usize;
+    // empty files can't be mapped
+    if src_size == 0 {
+        return compress_file(src, dst, None);
+    }
+
+    let map: MemoryMap = try!(map_file(&src_file, src_size, false));
+    let src_ptr: *const u8 = map.data() as *const u8;
+    let src_buf: &[u8] = unsafe { slice::from_raw_buf(&src_ptr, src_size) };
+
+    let dst_file = try!(File::create(dst));
+    let size: u64 = src_size as u64;
+    let mut compressor = match Compressor::with_content_size(dst_file, size, None, None) {
+        Ok(c) => c,
+        Err(lz4err) => { return Err(compress_error(lz4err.desc)); },
+    };
+    try!(compressor.compress_slices(&[src_buf]));
+    let (compressed, result) = compressor.done();
+    if let Err(lz4err) = result {
+        return Err(compress_error(lz4err.desc));
+    }
     let fstat = try!(compressed.stat());
     Ok(fstat.size as usize)
 }
@@ -221,6 +544,7 @@ pub fn compress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoResul
 
 
 /// determine the minimum value necessary for dstMaxSize
+#[cfg(not(feature = "pure-rust"))]
 pub fn compress_frame_bound(src_size: usize,
                             prefs: Option<&FramePreferences>) -> usize {
     let def_prefs: FramePreferences = Default::default();
@@ -232,19 +556,33 @@ pub fn compress_frame_bound(src_size: usize,
     bound as usize
 }
 
+/// determine the minimum value necessary for dstMaxSize
+#[cfg(feature = "pure-rust")]
+pub fn compress_frame_bound(src_size: usize,
+                            prefs: Option<&FramePreferences>) -> usize {
+    let def_prefs: FramePreferences = Default::default();
+    let prefs: &FramePreferences = match prefs {
+        Some(p) => p,
+        None => &def_prefs,
+    };
+    encoder::compress_frame_bound(src_size, prefs)
+}
+
 /// Compress an entire `src_buf` into a valid LZ4 frame, as defined by specification v1.5
-/// The most important rule is that `dst_buf` MUST be large enough (`dst_max_size`) to ensure
-/// compression completion even in worst case. You can get the minimum value of `dst_max_size`
-/// by using `compress_frame_bound()`.
+/// The most important rule is that `dst_buf` MUST be large enough to ensure compression
+/// completion even in worst case. You can get the minimum size of `dst_buf` by using
+/// `compress_frame_bound()`.
 /// If this condition is not respected, `compress_frame()` will fail with an `Lz4Error`.
 /// Providing `None` for `prefs` will result in default preferences being used.
 /// The result of the function is the number of bytes written into `dst_buf`.
+/// Block checksums and dictionary IDs need the `pure-rust` feature; this version of lz4frame can't
+/// write them.
+#[cfg(not(feature = "pure-rust"))]
 pub fn compress_frame(dst_buf: &mut [u8],
-                      dst_max_size: usize,
                       src_buf: &[u8],
                       prefs: Option<&FramePreferences>) -> Lz4Result<usize> {
     let src_size: size_t = src_buf.len() as size_t;
-    let dst_max_size: size_t = dst_max_size as size_t;
+    let dst_max_size: size_t = dst_buf.len() as size_t;
     let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
     let src_ptr: *const c_void = src_buf.as_ptr() as *const c_void;
     let def_prefs: FramePreferences = Default::default();
@@ -252,10 +590,42 @@ pub fn compress_frame(dst_buf: &mut [u8],
         Some(p) => p,
         None    => &def_prefs,
     };
+    try!(check_frame_info(prefs));
     let err = unsafe {
         LZ4F_compressFrame(dst_ptr, dst_max_size, src_ptr, src_size, prefs)
     };
-    maybe_error(err)
+    let len: usize = try!(maybe_error(err));
+    debug_assert!(len <= dst_buf.len());
+    Ok(len)
+}
+
+/// Compress an entire `src_buf` into a valid LZ4 frame, as defined by specification v1.5
+/// `dst_buf` MUST be large enough to ensure compression completion even in worst case, see
+/// `compress_frame_bound()`. Providing `None` for `prefs` will result in default preferences being
+/// used.
+/// The result of the function is the number of bytes written into `dst_buf`.
+#[cfg(feature = "pure-rust")]
+pub fn compress_frame(dst_buf: &mut [u8],
+                      src_buf: &[u8],
+                      prefs: Option<&FramePreferences>) -> Lz4Result<usize> {
+    let def_prefs: FramePreferences = Default::default();
+    let prefs: &FramePreferences = match prefs {
+        Some(p) => p,
+        None    => &def_prefs,
+    };
+    encoder::compress_frame(dst_buf, src_buf, prefs)
+}
+
+
+/// lz4frame writes the dictionary ID it is given into the header even without a dictionary to
+/// compress with, so such a frame is refused like the `FrameEncoder` refuses it.
+#[cfg(not(feature = "pure-rust"))]
+fn check_frame_info(prefs: &FramePreferences) -> Lz4Result<()> {
+    if prefs.frame_info.dict_id != 0 {
+        Err(dictionary_not_given(prefs.frame_info.dict_id))
+    } else {
+        Ok(())
+    }
 }
 
 
@@ -265,98 +635,260 @@ pub fn compress_frame(dst_buf: &mut [u8],
 
 /* Resource Management */
 
+/// An lz4frame compression context, which writes one frame at a time through `begin()`,
+/// `update()`, `flush()` and `end()`. After `end()`, it can be used again for another frame.
+#[cfg(not(feature = "pure-rust"))]
+pub struct CompressionContext {
+    ctx: Context,
+    in_frame: bool,
+}
+
+/// A frame compression context, which writes one frame at a time through `begin()`, `update()`,
+/// `flush()` and `end()`. With the `pure-rust` feature, the frame is written by a `FrameEncoder`.
+#[cfg(feature = "pure-rust")]
+pub struct CompressionContext {
+    encoder: FrameEncoder,
+    in_frame: bool,
+}
+
+/// See the `frame` module docs on sending contexts to other threads.
+#[cfg(not(feature = "pure-rust"))]
+unsafe impl Send for CompressionContext {}
+
+#[cfg(not(feature = "pure-rust"))]
+impl CompressionContext {
+    /// wrapper around `LZ4F_createCompressionContext()`
+    pub fn new() -> Lz4Result<CompressionContext> {
+        let mut ctx: Context = ptr::null_mut();
+        let err = unsafe { LZ4F_createCompressionContext(&mut ctx, LZ4F_VERSION) };
+        try!(maybe_error(err));
+        Ok(CompressionContext { ctx: ctx, in_frame: false })
+    }
+
+    /// Writes the frame header into `dst` and returns its size. Providing `None` for `prefs`
+    /// results in the default preferences being used.
+    pub fn begin(&mut self,
+                 dst: &mut [u8],
+                 prefs: Option<&FramePreferences>) -> Lz4Result<usize> {
+        let def_prefs: FramePreferences = Default::default();
+        let prefs: &FramePreferences = match prefs {
+            Some(p) => p,
+            None => &def_prefs,
+        };
+        try!(check_frame_info(prefs));
+        let dst_ptr: *mut c_void = dst.as_mut_ptr() as *mut c_void;
+        let err = unsafe {
+            LZ4F_compressBegin(self.ctx, dst_ptr, dst.len() as size_t, prefs)
+        };
+        let len: usize = try!(maybe_error(err));
+        debug_assert!(len <= dst.len());
+        self.in_frame = true;
+        Ok(len)
+    }
+
+    /// Compresses `src` into `dst` and returns the number of bytes written, which can be zero if
+    /// `src` was just buffered.
+    pub fn update(&mut self,
+                  dst: &mut [u8],
+                  src: &[u8],
+                  opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        let def_opts: FrameCompressOptions = Default::default();
+        let opts: &FrameCompressOptions = match opts {
+            Some(o) => o,
+            None => &def_opts,
+        };
+        let dst_ptr: *mut c_void = dst.as_mut_ptr() as *mut c_void;
+        let src_ptr: *const c_void = src.as_ptr() as *const c_void;
+        let err = unsafe {
+            LZ4F_compressUpdate(self.ctx,
+                                dst_ptr,
+                                dst.len() as size_t,
+                                src_ptr,
+                                src.len() as size_t,
+                                opts)
+        };
+        let len: usize = try!(maybe_error(err));
+        debug_assert!(len <= dst.len());
+        Ok(len)
+    }
+
+    /// Compresses any buffered data into `dst` and returns the number of bytes written.
+    pub fn flush(&mut self,
+                 dst: &mut [u8],
+                 opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        let def_opts: FrameCompressOptions = Default::default();
+        let opts: &FrameCompressOptions = match opts {
+            Some(o) => o,
+            None => &def_opts,
+        };
+        let dst_ptr: *mut c_void = dst.as_mut_ptr() as *mut c_void;
+        let err = unsafe {
+            LZ4F_flush(self.ctx, dst_ptr, dst.len() as size_t, opts)
+        };
+        let len: usize = try!(maybe_error(err));
+        debug_assert!(len <= dst.len());
+        Ok(len)
+    }
+
+    /// Compresses any buffered data into `dst` and finishes the frame, returning the number of
+    /// bytes written.
+    pub fn end(&mut self,
+               dst: &mut [u8],
+               opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        let def_opts: FrameCompressOptions = Default::default();
+        let opts: &FrameCompressOptions = match opts {
+            Some(o) => o,
+            None => &def_opts,
+        };
+        let dst_ptr: *mut c_void = dst.as_mut_ptr() as *mut c_void;
+        let err = unsafe {
+            LZ4F_compressEnd(self.ctx, dst_ptr, dst.len() as size_t, opts)
+        };
+        let len: usize = try!(maybe_error(err));
+        debug_assert!(len <= dst.len());
+        self.in_frame = false;
+        Ok(len)
+    }
+}
+
+/// Implements drop to ensure the underlying context is free'd properly.
+#[cfg(not(feature = "pure-rust"))]
+impl Drop for CompressionContext {
+    fn drop(&mut self) {
+        unsafe { LZ4F_freeCompressionContext(self.ctx); }
+    }
+}
+
+#[cfg(feature = "pure-rust")]
+impl CompressionContext {
+    /// Creates a new `CompressionContext`.
+    pub fn new() -> Lz4Result<CompressionContext> {
+        Ok(CompressionContext { encoder: FrameEncoder::new(), in_frame: false })
+    }
+
+    /// Writes the frame header into `dst` and returns its size. Providing `None` for `prefs`
+    /// results in the default preferences being used.
+    pub fn begin(&mut self,
+                 dst: &mut [u8],
+                 prefs: Option<&FramePreferences>) -> Lz4Result<usize> {
+        let def_prefs: FramePreferences = Default::default();
+        let prefs: &FramePreferences = match prefs {
+            Some(p) => p,
+            None => &def_prefs,
+        };
+        let len: usize = try!(self.encoder.begin(dst, prefs));
+        self.in_frame = true;
+        Ok(len)
+    }
+
+    /// Compresses `src` into `dst` and returns the number of bytes written, which can be zero if
+    /// `src` was just buffered. `opts` make no difference to the Rust implementation.
+    pub fn update(&mut self,
+                  dst: &mut [u8],
+                  src: &[u8],
+                  _opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        self.encoder.update(dst, src)
+    }
+
+    /// Compresses any buffered data into `dst` and returns the number of bytes written.
+    pub fn flush(&mut self,
+                 dst: &mut [u8],
+                 _opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        self.encoder.flush(dst)
+    }
+
+    /// Compresses any buffered data into `dst` and finishes the frame, returning the number of
+    /// bytes written.
+    pub fn end(&mut self,
+               dst: &mut [u8],
+               _opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
+        let len: usize = try!(self.encoder.end(dst));
+        self.in_frame = false;
+        Ok(len)
+    }
+}
+
+impl CompressionContext {
+    /// Whether a frame has been begun and not successfully ended yet. A context can only begin a
+    /// new frame once it has ended the last one, which is why `ContextPool` drops contexts handed
+    /// back in the middle of a frame.
+    pub fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+}
+
 /// wrapper around `LZ4F_createCompressionContext()`
-pub fn create_compression_context() -> Lz4Result<Lz4FrameContext> {
-    Lz4FrameContext::new(FrameContextType::Compression)
+pub fn create_compression_context() -> Lz4Result<CompressionContext> {
+    CompressionContext::new()
 }
 
 /* Compression */
 
-/// Writes the frame header into `dst_buf`. `dst_buf` must be large enough to accomadate a header
-/// (`dst_max_size`). Maximum header size is 15 bytes. Providing `None` for `prefs` results in the
+/// Writes the frame header into `dst_buf`, which must be large enough to accomadate a header.
+/// Maximum header size is 19 bytes. Providing `None` for `prefs` results in the
 /// default preferences being used. The result is either the number of bytes written into `dst_buf`
 /// for the header or an `Lz4Error`.
-pub fn compress_begin(cctx: &mut Lz4FrameContext,
+/// Block checksums and dictionary IDs need the `pure-rust` feature; this version of lz4frame can't
+/// write them.
+pub fn compress_begin(cctx: &mut CompressionContext,
                       dst_buf: &mut [u8],
-                      dst_max_size: usize,
                       prefs: Option<&FramePreferences>) -> Lz4Result<usize> {
+    cctx.begin(dst_buf, prefs)
+}
+
+/// Provides the minimum size of the destination buffer given `src_size` to handle worst case
+/// situations. Providing `None` for `prefs` results in the default preferences being used.
+/// Note that different preferences wil produce different results.
+#[cfg(not(feature = "pure-rust"))]
+pub fn compress_bound(src_size: usize, prefs: Option<&FramePreferences>) -> usize {
     let def_prefs: FramePreferences = Default::default();
     let prefs: &FramePreferences = match prefs {
         Some(p) => p,
         None => &def_prefs,
     };
-    let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
-    let dst_max_size: size_t = dst_max_size as size_t;
-    let err = unsafe {
-        LZ4F_compressBegin(cctx.ctx, dst_ptr, dst_max_size, prefs)
-    };
-    maybe_error(err)
+    let bound = unsafe { LZ4F_compressBound(src_size as size_t, prefs) };
+    bound as usize
 }
 
 /// Provides the minimum size of the destination buffer given `src_size` to handle worst case
 /// situations. Providing `None` for `prefs` results in the default preferences being used.
 /// Note that different preferences wil produce different results.
+#[cfg(feature = "pure-rust")]
 pub fn compress_bound(src_size: usize, prefs: Option<&FramePreferences>) -> usize {
     let def_prefs: FramePreferences = Default::default();
     let prefs: &FramePreferences = match prefs {
         Some(p) => p,
         None => &def_prefs,
     };
-    let bound = unsafe { LZ4F_compressBound(src_size as size_t, prefs) };
-    bound as usize
+    encoder::compress_bound(src_size, prefs)
 }
 
 /// `compress_update()` can be called repetitively to compress as much data as necessary. The most
-/// important rule is that `dst_buf` MUST be large enough (`dst_max_size`) to ensure compression
-/// completion even in worst case.
+/// important rule is that `dst_buf` MUST be large enough to ensure compression completion even in
+/// worst case.
 /// If this condition is not respected, `compress_update()` will fail with an `Lz4Error`. You can
-/// get the minimum value of `dst_max_size` by using `compress_bound()`. If `None` is provided
+/// get the minimum size of `dst_buf` by using `compress_bound()`. If `None` is provided
 /// for `compress_opts`, the default compression options are used. 
 /// The result of the function is the number of bytes written into `dst_buf`. It can be zero,
 /// meaning input data was just buffered.
-pub fn compress_update(cctx: &mut Lz4FrameContext,
+pub fn compress_update(cctx: &mut CompressionContext,
                        dst_buf: &mut [u8],
-                       dst_max_size: usize,
                        src_buf: &[u8],
-                       src_size: usize,
                        compress_opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
-    let def_opts = Default::default();
-    let compress_opts: &FrameCompressOptions = match compress_opts {
-        Some(c) => c,
-        None => &def_opts,
-    };
-    let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
-    let dst_max_size: size_t = dst_max_size as size_t;
-    let src_ptr: *const c_void = src_buf.as_ptr() as *const c_void;
-    let src_size: size_t = src_size as size_t;
-    let err = unsafe {
-        LZ4F_compressUpdate(cctx.ctx, dst_ptr, dst_max_size, src_ptr, src_size, compress_opts)
-    };
-    maybe_error(err)
+    cctx.update(dst_buf, src_buf, compress_opts)
 }
 
 /// Should you need to generate compressed data immediately, without waiting for the current block
 /// to be filled, you can call `flush()`, which will immediately compress any remaining data
 /// buffered within `cctx`.
-/// Note that `dst_max_size` must be large enough to ensure the operation will be successful.
+/// Note that `dst_buf` must be large enough to ensure the operation will be successful.
 /// If `None` is provided for `compress_opts`, the default compression options will be used.
 /// The result of the function is either the number of bytes written into `dst_buffer` 
 /// (which can be zero, meaning there was no data left within `cctx`) or an `Lz4Error`
-pub fn flush(cctx: &mut Lz4FrameContext,
+pub fn flush(cctx: &mut CompressionContext,
              dst_buf: &mut [u8],
-             dst_max_size: usize,
              compress_opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
-    let def_opts: FrameCompressOptions = Default::default();
-    let compress_opts: &FrameCompressOptions = match compress_opts {
-        Some(c) => c,
-        None => &def_opts,
-    };
-    let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
-    let dst_max_size: size_t = dst_max_size as size_t;
-    let err = unsafe {
-        LZ4F_flush(cctx.ctx, dst_ptr, dst_max_size, compress_opts)
-    };
-    maybe_error(err)
+    cctx.flush(dst_buf, compress_opts)
 }
 
 /// When you want to properly finish the compressed frame, just call `compress_end()`. It will
@@ -365,19 +897,8 @@ pub fn flush(cctx: &mut Lz4FrameContext,
 /// compression options will be used.
 /// The result of the function is either the number of bytes written into `dst_buf`
 /// (necessarily >= 4 (`endMark` size)) or an `Lz4Error`.
-pub fn compress_end(cctx: &mut Lz4FrameContext,
+pub fn compress_end(cctx: &mut CompressionContext,
                     dst_buf: &mut [u8],
-                    dst_max_size: usize,
                     compress_opts: Option<&FrameCompressOptions>) -> Lz4Result<usize> {
-    let def_opts: FrameCompressOptions = Default::default();
-    let compress_opts: &FrameCompressOptions = match compress_opts {
-        Some(c) => c,
-        None => &def_opts,
-    };
-    let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
-    let dst_max_size: size_t = dst_max_size as size_t;
-    let err = unsafe {
-        LZ4F_compressEnd(cctx.ctx, dst_ptr, dst_max_size, compress_opts)
-    };
-    maybe_error(err)
+    cctx.end(dst_buf, compress_opts)
 }
@@ -0,0 +1,371 @@
+//! A Rust implementation of lz4frame's decompression functions, used by the `Decompressor` and the
+//! wrappers in `decompress` when the `pure-rust` feature is enabled, and exported as `frame::rust`.
+//! Frames are decoded incrementally from whatever input is provided, following the frame format
+//! specification: the header checksum, block checksums and content checksum are all verified, and
+//! skippable frames are skipped. A block whose checksum doesn't match is reported by its index in
+//! the frame.
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::cmp::min;
+use std::iter::repeat;
+use std::slice::bytes::copy_memory;
+
+use lz4rs_sys::{
+    FrameInfo,
+    BlockMode,
+    ContentChecksum,
+    FrameType,
+};
+
+use super::super::core::block;
+use super::super::xxhash::{xxh32, Xxh32};
+
+use super::header::{
+    FrameHeader,
+    BLOCK_HEADER_SIZE,
+    CHECKSUM_SIZE,
+    UNCOMPRESSED_FLAG,
+    required_header_size,
+    read_header,
+    read_u32,
+    block_checksum_mismatch,
+    content_checksum_mismatch,
+    dictionary_not_given,
+};
+
+use super::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+/// How much previously decoded data blocks of a frame with linked blocks may refer back to.
+const WINDOW_SIZE: usize = 64 * 1024;
+
+/// The part of a frame the `FrameDecoder` expects next.
+#[derive(Copy, Clone, PartialEq)]
+enum Stage {
+    Header,
+    BlockHeader,
+    Block(usize, bool),
+    Flush,
+    ContentChecksum,
+    Skip(usize),
+}
+
+/// Decompression state of a single frame, the counterpart of an `LZ4F_decompressionContext_t`.
+pub struct FrameDecoder {
+    stage: Stage,
+    header: Option<FrameHeader>,
+    staging: Vec<u8>,
+    window: Vec<u8>,
+    prefix_len: usize,
+    out_start: usize,
+    out_end: usize,
+    content_checksum: Option<Xxh32>,
+    total_out: u64,
+    blocks: u64,
+    legacy_header_checksum: bool,
+    /// Whether the last `decompress` call found the content checksum not to match.
+    checksum_failed: bool,
+}
+
+impl FrameDecoder {
+    /// Creates a `FrameDecoder` expecting the start of a frame.
+    pub fn new() -> FrameDecoder {
+        FrameDecoder {
+            stage: Stage::Header,
+            header: None,
+            staging: Vec::new(),
+            window: Vec::new(),
+            prefix_len: 0,
+            out_start: 0,
+            out_end: 0,
+            content_checksum: None,
+            total_out: 0,
+            blocks: 0,
+            legacy_header_checksum: false,
+            checksum_failed: false,
+        }
+    }
+
+    /// Sets whether frame headers are expected to carry Kafka's legacy header checksum, which
+    /// covers the magic number too.
+    pub fn set_legacy_header_checksum(&mut self, legacy: bool) {
+        self.legacy_header_checksum = legacy;
+    }
+
+    /// Decodes the frame header from `src` if that hasn't happened yet, without decompressing
+    /// anything. The result is the decoded `FrameInfo`, the number of bytes consumed from `src` and
+    /// a hint of how many bytes `decompress` expects next. If the header is already known, nothing
+    /// is consumed.
+    pub fn frame_info(&mut self, src: &[u8]) -> Lz4Result<(FrameInfo, usize, usize)> {
+        let mut ip: usize = 0;
+        if self.stage == Stage::Header {
+            try!(self.decode_header(src, &mut ip));
+        }
+        match self.header {
+            Some(ref header) if self.stage != Stage::Header => {
+                Ok((header.info, ip, self.hint()))
+            },
+            _ => Err(Lz4Error { desc: "frame header incomplete".to_string() }),
+        }
+    }
+
+    /// Whether the last `decompress` call failed because the frame's content checksum didn't match
+    /// its content.
+    pub fn content_checksum_failed(&self) -> bool {
+        self.checksum_failed
+    }
+
+    /// Returns the ID of the dictionary the current frame was compressed with, if its header
+    /// names one.
+    pub fn dict_id(&self) -> Option<u32> {
+        match self.header {
+            Some(ref header) => header.dict_id,
+            None => None,
+        }
+    }
+
+    /// Decodes as much of `src` into `dst` as possible. The result is the number of bytes consumed
+    /// from `src`, the number of bytes written into `dst` and a hint of how many bytes are expected
+    /// next, which is 0 once the frame is complete. Decoding stops at the end of a frame; calling
+    /// `decompress` again afterwards starts the next frame. A frame naming a dictionary fails
+    /// after its header.
+    pub fn decompress(&mut self, dst: &mut [u8], src: &[u8]) -> Lz4Result<(usize, usize, usize)> {
+        let mut ip: usize = 0;
+        let mut op: usize = 0;
+        self.checksum_failed = false;
+
+        loop {
+            match self.stage {
+                Stage::Header => {
+                    if !try!(self.decode_header(src, &mut ip)) { break; }
+                },
+                Stage::BlockHeader => {
+                    if let Some(dict_id) = self.dict_id() {
+                        return Err(dictionary_not_given(dict_id));
+                    }
+                    if !self.fill(src, &mut ip, BLOCK_HEADER_SIZE) { break; }
+                    let value: u32 = read_u32(self.staging.as_slice(), 0);
+                    self.staging.clear();
+                    if value == 0 {
+                        self.stage = Stage::ContentChecksum;
+                        continue;
+                    }
+                    let size: usize = (value & !UNCOMPRESSED_FLAG) as usize;
+                    if size > self.block_size() {
+                        return Err(Lz4Error { desc: "block larger than maximum block size"
+                                                    .to_string() });
+                    }
+                    self.stage = Stage::Block(size, value & UNCOMPRESSED_FLAG == 0);
+                },
+                Stage::Block(size, compressed) => {
+                    let needed: usize = size + if self.has_block_checksum() { CHECKSUM_SIZE }
+                                               else { 0 };
+                    // decode straight from `src` when the whole block is there
+                    if self.staging.len() == 0 && src.len() - ip >= needed {
+                        try!(self.decode_block(src.slice(ip, ip + needed), size, compressed));
+                        ip += needed;
+                    } else {
+                        if !self.fill(src, &mut ip, needed) { break; }
+                        let staged: Vec<u8> = ::std::mem::replace(&mut self.staging, Vec::new());
+                        try!(self.decode_block(staged.as_slice(), size, compressed));
+                        self.staging = staged;
+                        self.staging.clear();
+                    }
+                    self.stage = Stage::Flush;
+                },
+                Stage::Flush => {
+                    let n: usize = min(self.out_end - self.out_start, dst.len() - op);
+                    copy_memory(dst.slice_mut(op, op + n),
+                                self.window.slice(self.out_start, self.out_start + n));
+                    op += n;
+                    self.out_start += n;
+                    if self.out_start < self.out_end { break; }
+                    self.slide_window();
+                    self.stage = Stage::BlockHeader;
+                },
+                Stage::ContentChecksum => {
+                    let checksum: Option<u32> = match self.content_checksum {
+                        Some(ref hasher) => Some(hasher.digest()),
+                        None => None,
+                    };
+                    if let Some(c) = checksum {
+                        if !self.fill(src, &mut ip, CHECKSUM_SIZE) { break; }
+                        let stored: u32 = read_u32(self.staging.as_slice(), 0);
+                        self.staging.clear();
+                        if stored != c {
+                            self.checksum_failed = true;
+                            return Err(content_checksum_mismatch());
+                        }
+                    }
+                    try!(self.finish_frame());
+                    return Ok((ip, op, 0));
+                },
+                Stage::Skip(remaining) => {
+                    let n: usize = min(remaining, src.len() - ip);
+                    ip += n;
+                    if n < remaining {
+                        self.stage = Stage::Skip(remaining - n);
+                        break;
+                    }
+                    try!(self.finish_frame());
+                    return Ok((ip, op, 0));
+                },
+            }
+        }
+        debug_assert!(ip <= src.len() && op <= dst.len());
+        Ok((ip, op, self.hint()))
+    }
+
+    /// Stages header bytes from `src` until the whole header is there, then parses it. Returns
+    /// whether the header is complete.
+    fn decode_header(&mut self, src: &[u8], ip: &mut usize) -> Lz4Result<bool> {
+        loop {
+            let needed: usize = required_header_size(self.staging.as_slice());
+            if !self.fill(src, ip, needed) { return Ok(false); }
+            if required_header_size(self.staging.as_slice()) == needed { break; }
+        }
+        let legacy: bool = self.legacy_header_checksum;
+        let (header, _) = match try!(read_header(self.staging.as_slice(), legacy)) {
+            Some(h) => h,
+            None => { return Ok(false); },
+        };
+        self.staging.clear();
+
+        self.stage = if header.info.frame_type == FrameType::SkippableFrame {
+            Stage::Skip(header.skip_size)
+        } else {
+            Stage::BlockHeader
+        };
+        self.content_checksum = if header.info.content_checksum_flag == ContentChecksum::Enabled {
+            Some(Xxh32::new(0))
+        } else {
+            None
+        };
+        self.prefix_len = 0;
+        self.out_start = 0;
+        self.out_end = 0;
+        self.total_out = 0;
+        self.blocks = 0;
+        self.header = Some(header);
+        Ok(true)
+    }
+
+    /// Verifies the checksum of a block and decodes it into the window, after the data it may
+    /// refer back to.
+    fn decode_block(&mut self, src: &[u8], size: usize, compressed: bool) -> Lz4Result<()> {
+        let data: &[u8] = src.slice_to(size);
+        if self.has_block_checksum() {
+            if read_u32(src, size) != xxh32(data, 0) {
+                return Err(block_checksum_mismatch(self.blocks));
+            }
+        }
+
+        let prefix: usize = self.prefix_len;
+        let end: usize = prefix + self.block_size();
+        if self.window.len() < end {
+            let extra: usize = end - self.window.len();
+            self.window.extend(repeat(0u8).take(extra));
+        }
+        let len: usize = if compressed {
+            let window: &mut [u8] = self.window.slice_to_mut(end);
+            try!(block::rust::decompress_with_prefix(data, window, prefix))
+        } else {
+            copy_memory(self.window.slice_mut(prefix, prefix + size), data);
+            size
+        };
+
+        if let Some(ref mut hasher) = self.content_checksum {
+            hasher.update(self.window.slice(prefix, prefix + len));
+        }
+        self.total_out += len as u64;
+        self.blocks += 1;
+        self.out_start = prefix;
+        self.out_end = prefix + len;
+        Ok(())
+    }
+
+    /// Once a block has been handed out, keeps the last `WINDOW_SIZE` bytes decoded at the start
+    /// of the window if the next block may refer back to them.
+    fn slide_window(&mut self) {
+        let linked: bool = match self.header {
+            Some(ref header) => header.info.block_mode == BlockMode::Linked,
+            None => false,
+        };
+        if !linked {
+            self.prefix_len = 0;
+            return;
+        }
+        let keep: usize = min(self.out_end, WINDOW_SIZE);
+        let start: usize = self.out_end - keep;
+        if start >= keep {
+            let (front, back) = self.window.split_at_mut(start);
+            copy_memory(front.slice_to_mut(keep), back.slice_to(keep));
+        } else {
+            for i in 0..keep {
+                self.window[i] = self.window[start + i];
+            }
+        }
+        self.prefix_len = keep;
+    }
+
+    /// Checks the declared content size at the end of a frame and gets ready for the next one.
+    fn finish_frame(&mut self) -> Lz4Result<()> {
+        self.stage = Stage::Header;
+        if let Some(ref header) = self.header {
+            let declared: u64 = header.info.content_size;
+            if declared != 0 && declared != self.total_out {
+                return Err(Lz4Error {
+                    desc: format!("frame declared {} bytes of content, got {}",
+                                  declared, self.total_out),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves bytes from `src` into the staging buffer until it holds `needed` bytes. Returns
+    /// whether it does.
+    fn fill(&mut self, src: &[u8], ip: &mut usize, needed: usize) -> bool {
+        if self.staging.len() < needed {
+            let n: usize = min(needed - self.staging.len(), src.len() - *ip);
+            self.staging.push_all(src.slice(*ip, *ip + n));
+            *ip += n;
+        }
+        self.staging.len() >= needed
+    }
+
+    /// How many bytes `decompress` wants next: the rest of the current part of the frame, and for
+    /// blocks also the header of the next one.
+    fn hint(&self) -> usize {
+        let staged: usize = self.staging.len();
+        match self.stage {
+            Stage::Header => required_header_size(self.staging.as_slice()) - staged,
+            Stage::BlockHeader => BLOCK_HEADER_SIZE - staged,
+            Stage::Block(size, _) => {
+                let checksum: usize = if self.has_block_checksum() { CHECKSUM_SIZE } else { 0 };
+                size + checksum - staged + BLOCK_HEADER_SIZE
+            },
+            Stage::Flush => BLOCK_HEADER_SIZE,
+            Stage::ContentChecksum => CHECKSUM_SIZE - staged,
+            Stage::Skip(remaining) => remaining,
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        match self.header {
+            Some(ref header) => header.info.block_size_id.bytes(),
+            None => 0,
+        }
+    }
+
+    fn has_block_checksum(&self) -> bool {
+        match self.header {
+            Some(ref header) => header.block_checksum,
+            None => false,
+        }
+    }
+}
@@ -3,70 +3,367 @@
 //! are read out and decompressed before returning the number of bytes read.
 //! Additionally, safe wrappers for all of the public decompression functions from `lz4frame.h`
 //! are provided.
+//! The `Decompressor` and `decompress_file` need the `std` feature; the slice-based functions
+//! don't.
 
 extern crate libc;
 extern crate collections;
 
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+#[cfg(not(feature = "pure-rust"))]
 use libc::{size_t, c_void};
 
-use std::io::{IoResult, IoError, IoErrorKind};
+#[cfg(feature = "std")]
+use std::io::{IoResult, IoError, IoErrorKind, Seek, SeekStyle};
+#[cfg(feature = "std")]
 use std::io::fs::File;
 
 use std::default::Default;
+use std::cmp::min;
+#[cfg(not(feature = "pure-rust"))]
+use std::ptr;
+use std::slice::bytes::copy_memory;
+#[cfg(feature = "std")]
+use std::iter::repeat;
+#[cfg(feature = "std")]
+use std::mem;
 
-use super::super::liblz4::frame::types::{
+use lz4rs_sys::{
     FrameInfo,
+    FrameType,
+    BlockMode,
+    ContentChecksum,
+};
+
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::{
+    Context,
     FrameDecompressOptions,
+    BlockChecksum,
+    CONTENT_CHECKSUM_INVALID,
+    BLOCK_CHECKSUM_INVALID,
 };
 
-use super::super::liblz4::frame::{
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::{
+    LZ4F_VERSION,
+    LZ4F_createDecompressionContext,
+    LZ4F_freeDecompressionContext,
     LZ4F_getFrameInfo,
     LZ4F_decompress,
 };
 
 use super::structs::{
+    Lz4Error,
     Lz4Result,
-    FrameContextType,
-    Lz4FrameContext,
+    DecompressOptions,
 };
+#[cfg(feature = "std")]
+use super::structs::FrameStatus;
+#[cfg(feature = "std")]
+use super::structs::context_in_frame;
 
-use super::{
-    is_error,
-    maybe_error,
+use super::header::{
+    FrameHeader,
+    BLOCK_HEADER_SIZE,
+    CHECKSUM_SIZE,
+    UNCOMPRESSED_FLAG,
+    required_header_size,
+    read_header,
+    read_u32,
+    block_checksum_mismatch,
+    content_checksum_mismatch,
+    dictionary_not_given,
 };
+#[cfg(feature = "std")]
+
+#[cfg(not(feature = "pure-rust"))]
+use super::header::rewrite_header_checksum;
+
+use super::super::core::block;
+use super::super::xxhash::{xxh32, Xxh32};
+
+#[cfg(not(feature = "pure-rust"))]
+use super::maybe_error;
+
+#[cfg(feature = "pure-rust")]
+use super::decoder::FrameDecoder;
+
+#[cfg(all(feature = "mmap", unix))]
+use collections::slice;
+#[cfg(all(feature = "mmap", unix))]
+use std::io::{FileMode, FileAccess};
+#[cfg(all(feature = "mmap", unix))]
+use std::os::MemoryMap;
+#[cfg(all(feature = "mmap", unix))]
+use super::map_file;
+#[cfg(all(feature = "mmap", unix))]
+use std::io::fs;
+#[cfg(all(feature = "mmap", unix))]
+use std::usize;
 
-const DEFAULT_BUF_SIZE: usize = 1024;
+/// The most decompressed data a compressed byte can stand for: every byte a match spends on its
+/// length makes it at most 255 bytes longer.
+#[cfg(all(feature = "mmap", unix))]
+const MAX_RATIO: u64 = 255;
 
+/// How big the buffer `decompress_file` decompresses into is by default.
+#[cfg(feature = "std")]
+const DEFAULT_BUF_SIZE: usize = 64 * 1024;
+
+/// How many compressed bytes `Decompressor` reads before lz4frame has told it how many it wants:
+/// the size of the smallest possible frame header.
+#[cfg(feature = "std")]
+const MIN_HEADER_SIZE: usize = 7;
+
+/// Bytes besides the compressed data itself that lz4frame may ask for together with a block: the
+/// block's checksum and the header of the following block.
+#[cfg(feature = "std")]
+const BLOCK_OVERHEAD: usize = 8;
+
+#[cfg(feature = "std")]
 pub struct Decompressor<R> {
     inner: R,
-    dctx: Lz4FrameContext,
+    dctx: DecompressionContext,
     buffer: Vec<u8>,
     buf_size: usize,
     eof: bool,
     buf_offset: usize,
+    next_src_size: usize,
+    frame_info: Option<FrameInfo>,
+    opts: DecompressOptions,
 }
 
 /// Decpmpressor struct implementation
+#[cfg(feature = "std")]
 impl<R: Reader> Decompressor<R> {
+    /// Creates a new `Decompressor` reading a frame from `src`. `buf_size` is the initial size of
+    /// the buffer compressed data is read into. Either way, the buffer is grown to hold a whole
+    /// block once the frame header has been decoded, and each read from `src` asks for exactly as
+    /// many bytes as lz4frame hinted it needs next.
     pub fn new(src: R, buf_size: Option<usize>) -> Lz4Result<Decompressor<R>> {
-        let ctx: Lz4FrameContext = try!(create_decompression_context());
+        Decompressor::with_options(src, buf_size, None)
+    }
 
-        let size: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
+    /// Creates a new `Decompressor` like `new`, which passes `opts` to `decompress`. With
+    /// `legacy_header_checksum` set, e.g. to read frames written by old Kafka versions, the whole
+    /// frame header is read before decompression starts.
+    pub fn with_options(src: R,
+                        buf_size: Option<usize>,
+                        opts: Option<DecompressOptions>) -> Lz4Result<Decompressor<R>> {
+        let ctx: DecompressionContext = try!(create_decompression_context());
+        Decompressor::with_context(src, ctx, buf_size, opts)
+    }
+
+    /// Creates a `Decompressor` like `with_options` that decompresses with `ctx` instead of
+    /// creating a context of its own, e.g. one taken from a `ContextPool`. Fails if `ctx` is in
+    /// the middle of a frame. `into_parts` hands it back.
+    pub fn with_context(src: R,
+                        ctx: DecompressionContext,
+                        buf_size: Option<usize>,
+                        opts: Option<DecompressOptions>) -> Lz4Result<Decompressor<R>> {
+        if ctx.in_frame() {
+            return Err(context_in_frame());
+        }
+        let size: usize = buf_size.unwrap_or(MIN_HEADER_SIZE);
+        let mut opts: DecompressOptions = opts.unwrap_or_else(Default::default);
+        // the buffers read into are gone after each call, so lz4frame has to keep its own copy
+        opts.frame = Default::default();
 
-        let mut buf: Vec<u8> = Vec::with_capacity(size);
-        unsafe { buf.set_len(size); }
+        let buf: Vec<u8> = repeat(0u8).take(size).collect();
 
         Ok(Decompressor {
             inner: src,
             dctx: ctx,
             buffer: buf,
-            buf_size: size,
+            buf_size: 0,
             eof: false,
-            buf_offset: size,
+            buf_offset: 0,
+            next_src_size: MIN_HEADER_SIZE,
+            frame_info: None,
+            opts: opts,
         })
     }
+
+    /// Unwraps this `Decompressor`, returning the inner `Reader` and the decompression context,
+    /// e.g. to hand it back to a `ContextPool`. Unless the frame has been read to its end, the
+    /// context is still in the middle of it and can't be used for another frame.
+    pub fn into_parts(self) -> (R, DecompressionContext) {
+        (self.inner, self.dctx)
+    }
+
+    /// Unwraps this `Decompressor`, returning the inner `Reader` together with the bytes read from
+    /// it that weren't decompressed yet. Once the frame has been read to its end, these are the
+    /// bytes following it, which the inner `Reader` continues after.
+    pub fn into_inner(self) -> (R, Vec<u8>) {
+        let trailing: Vec<u8> = self.buffer.slice(self.buf_offset, self.buf_size).to_vec();
+        (self.inner, trailing)
+    }
+
+    /// Grows the internal buffer so that it can hold at least `size` bytes.
+    fn grow_buffer(&mut self, size: usize) {
+        if size > self.buffer.len() {
+            let additional: usize = size - self.buffer.len();
+            self.buffer.extend(repeat(0u8).take(additional));
+        }
+    }
+
+    /// Reads the number of compressed bytes lz4frame asked for last into the internal buffer,
+    /// returning how many were actually read. This is less than asked for only if the inner
+    /// `Reader` reached EoF.
+    fn fill_buffer(&mut self) -> IoResult<usize> {
+        let want: usize = self.next_src_size;
+        self.grow_buffer(want);
+
+        let mut filled: usize = 0;
+        while filled < want {
+            match self.inner.read(self.buffer.slice_mut(filled, want)) {
+                Ok(n) => { filled += n; },
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+                Err(e) => { return Err(e); },
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Decompresses the first `n` bytes of the frame without decoding the rest of its first block,
+    /// e.g. to look at a header at the start of the content. Fewer bytes are returned if the first
+    /// block is shorter, and none for a skippable frame. This has to be called before anything is
+    /// read; the compressed data is kept, so reading afterwards still starts at the beginning of
+    /// the frame.
+    pub fn read_prefix(&mut self, n: usize) -> IoResult<Vec<u8>> {
+        if self.buf_size != 0 || self.frame_info.is_some() {
+            return Err(decompress_error("read_prefix called after reading".to_string()));
+        }
+
+        // frame header
+        let header_size: usize = try!(self.fill_header());
+        let legacy: bool = self.opts.legacy_header_checksum;
+        let header: FrameHeader = match read_header(self.buffer.slice_to(self.buf_size), legacy) {
+            Ok(Some((header, _))) => header,
+            Ok(None) => { return Err(decompress_error("frame truncated".to_string())); },
+            Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+        };
+        if header.info.frame_type == FrameType::SkippableFrame {
+            return Ok(Vec::new());
+        }
+        if let Some(dict_id) = header.dict_id {
+            return Err(decompress_error(dictionary_not_given(dict_id).desc));
+        }
+
+        // first block
+        try!(self.fill_to(header_size + BLOCK_HEADER_SIZE));
+        let value: u32 = read_u32(self.buffer.as_slice(), header_size);
+        // end mark: the frame is empty
+        if value == 0 {
+            return Ok(Vec::new());
+        }
+        let size: usize = (value & !UNCOMPRESSED_FLAG) as usize;
+        let block_size: usize = header.info.block_size_id.bytes();
+        if size > block_size {
+            return Err(decompress_error("block larger than maximum block size".to_string()));
+        }
+        let start: usize = header_size + BLOCK_HEADER_SIZE;
+        let checksum_size: usize = if header.block_checksum { CHECKSUM_SIZE } else { 0 };
+        try!(self.fill_to(start + size + checksum_size));
+        let data: &[u8] = self.buffer.slice(start, start + size);
+        if header.block_checksum &&
+           read_u32(self.buffer.as_slice(), start + size) != xxh32(data, 0) {
+            return Err(decompress_error(block_checksum_mismatch(0).desc));
+        }
+
+        let target: usize = min(n, block_size);
+        if value & UNCOMPRESSED_FLAG != 0 {
+            return Ok(data.slice_to(min(target, size)).to_vec());
+        }
+        // lz4 before 1.9 fails when a literal run or match crosses the end of `dst`, so the block
+        // gets room for all of its content, of which only the prefix is decoded and kept
+        let mut scratch: Vec<u8> = repeat(0u8).take(block_size).collect();
+        match block::decompress_partial(data, scratch.as_mut_slice(), target) {
+            Ok(len) => Ok(scratch.slice_to(len).to_vec()),
+            Err(lz4err) => Err(decompress_error(lz4err.desc)),
+        }
+    }
+
+    /// Returns the content size the frame declares in its header, or `None` if it doesn't declare
+    /// one, e.g. to preallocate or show progress before decompressing. If nothing has been read
+    /// yet, the header is read ahead; reading afterwards still starts at the beginning of the
+    /// frame.
+    pub fn content_size(&mut self) -> IoResult<Option<u64>> {
+        let size: u64 = match self.frame_info {
+            Some(ref finfo) => finfo.content_size,
+            None if self.buf_offset == 0 => {
+                try!(self.fill_header());
+                let legacy: bool = self.opts.legacy_header_checksum;
+                match read_header(self.buffer.slice_to(self.buf_size), legacy) {
+                    Ok(Some((header, _))) => header.info.content_size,
+                    Ok(None) => { return Err(decompress_error("frame truncated".to_string())); },
+                    Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+                }
+            },
+            None => { return Err(decompress_error("frame header incomplete".to_string())); },
+        };
+        Ok(if size != 0 { Some(size) } else { None })
+    }
+
+    /// Reads from the inner `Reader` until the internal buffer holds the whole frame header,
+    /// returning its size.
+    fn fill_header(&mut self) -> IoResult<usize> {
+        let mut header_size: usize = MIN_HEADER_SIZE;
+        loop {
+            try!(self.fill_to(header_size));
+            let size: usize = required_header_size(self.buffer.slice_to(self.buf_size));
+            if size == header_size { return Ok(header_size); }
+            header_size = size;
+        }
+    }
+
+    /// Reads from the inner `Reader` until the internal buffer holds `len` bytes, failing if it
+    /// reaches EoF first.
+    fn fill_to(&mut self, len: usize) -> IoResult<()> {
+        self.grow_buffer(len);
+        while self.buf_size < len {
+            match self.inner.read(self.buffer.slice_mut(self.buf_size, len)) {
+                Ok(n) => { self.buf_size += n; },
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => {
+                    return Err(decompress_error("frame truncated".to_string()));
+                },
+                Err(e) => { return Err(e); },
+            }
+        }
+        Ok(())
+    }
+
+    /// Once lz4frame has decoded the frame header, keeps the `FrameInfo` and grows the internal
+    /// buffer to fit a whole compressed block, so that blocks can be decompressed without being
+    /// copied into lz4frame's own buffers first.
+    fn load_frame_info(&mut self) {
+        if let Ok((finfo, _, _)) = get_frame_info(&mut self.dctx, &[]) {
+            let size: usize = finfo.block_size_id.bytes() + BLOCK_OVERHEAD;
+            self.grow_buffer(size);
+            self.frame_info = Some(finfo);
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl<R: Reader + Seek> Decompressor<R> {
+    /// Unwraps this `Decompressor` like `into_inner`, but seeks the inner `Reader` back over the
+    /// bytes that weren't decompressed instead of returning them. Once the frame has been read to
+    /// its end, the inner `Reader` is left at the byte right after it.
+    pub fn into_inner_rewound(self) -> IoResult<R> {
+        let unread: usize = self.buf_size - self.buf_offset;
+        let mut inner: R = self.inner;
+        if unread != 0 {
+            try!(inner.seek(-(unread as i64), SeekStyle::SeekCur));
+        }
+        Ok(inner)
+    }
+}
+
+#[cfg(feature = "std")]
 impl<R: Reader> Reader for Decompressor<R> {
 
     /// read the decompressor
@@ -82,28 +379,36 @@ impl<R: Reader> Reader for Decompressor<R> {
             });
         }
 
+        // the header has to reach `decompress` in one piece to have its legacy checksum checked
+        let legacy: bool = self.opts.legacy_header_checksum;
+        if legacy && self.frame_info.is_none() && self.buf_size == 0 {
+            try!(self.fill_header());
+        }
+
         // offset indicating where we are currently in the provided buffer `buf'
         let mut dst_offset: usize = 0;
 
-        // while our position in `buf' is < its length
-        while dst_offset < buf.len() {
+        // while our position in `buf' is < its length and the frame hasn't ended
+        while dst_offset < buf.len() && !self.eof {
 
-            // where we read up to `self.buffer.len()` compressed bytes into self.buffer
-            // self.buffer.len == self.buf_size
+            // once everything in `self.buffer' is consumed, read as many compressed bytes as
+            // lz4frame hinted it wants next
             if self.buf_offset >= self.buf_size {
                 // set our position in `self.buffer' to 0
                 self.buf_offset = 0;
-                // read up to `self.buffer.len()' compressed bytes into `self.buffer'
-                match self.inner.read(self.buffer.as_mut_slice()) {
-                    // sets self.buf_size to number of bytes actually read `n'
-                    Ok(n) => { self.buf_size = n; },
-                    // got EoF, len == 0, no bytes read?
-                    Err(ref e) if e.kind == IoErrorKind::EndOfFile => { self.buf_size = 0; },
-                    // other err, just return it
-                    Err(e) => { return Err(e); }
+                self.buf_size = try!(self.fill_buffer());
+                // if we hit EoF, return what has been decompressed so far, and fail the next call
+                if self.buf_size == 0 {
+                    if dst_offset != 0 { break; }
+                    if self.dctx.in_frame() {
+                        return Err(decompress_error(truncated_frame().desc));
+                    }
+                    return Err(IoError {
+                        kind: IoErrorKind::EndOfFile,
+                        desc: "No more to decompress",
+                        detail: None,
+                    });
                 }
-                // if we hit EoF, break
-                if self.buf_size <= 0 { break; }
             }
 
             // while:
@@ -113,22 +418,16 @@ impl<R: Reader> Reader for Decompressor<R> {
             while (dst_offset < buf.len()) && (self.buf_offset < self.buf_size) {
 
                 // we will attempt to decompress remaining amount of bytes in self.buffer
-                let mut src_size: usize = self.buf_size - self.buf_offset;
-
-                let src_buf: &[u8] = self.buffer.slice_from(self.buf_offset);
-
-                let mut dst_size: usize = buf.len() - dst_offset;
-                let mut dst_buf: &mut [u8] = buf.slice_from_mut(dst_offset);
-
-                match decompress(&mut self.dctx,
-                                 dst_buf,
-                                 &mut dst_size,
-                                 src_buf,
-                                 &mut src_size,
-                                 None) {
-                    Ok(len) => {
-                        self.buf_offset += src_size;
-                        dst_offset += dst_size;
+                let src_buf: &[u8] = self.buffer.slice(self.buf_offset, self.buf_size);
+                let dst_buf: &mut [u8] = buf.slice_from_mut(dst_offset);
+
+                match decompress(&mut self.dctx, dst_buf, src_buf, Some(&self.opts)) {
+                    Ok((consumed, written, len)) => {
+                        self.buf_offset += consumed;
+                        dst_offset += written;
+                        debug_assert!(self.buf_offset <= self.buf_size && dst_offset <= buf.len());
+                        self.next_src_size = len;
+                        if self.frame_info.is_none() { self.load_frame_info(); }
                         // no more data expected to decompress
                         if len == 0 { self.eof = true; break; }
                     },
@@ -146,40 +445,401 @@ impl<R: Reader> Reader for Decompressor<R> {
     }
 }
 
+/// Wraps a `Writer`, decompressing the LZ4 frame written to it and writing the decompressed data
+/// to the inner `Writer`. The frame can be written in slices of any size, e.g. as it arrives.
+#[cfg(feature = "std")]
+pub struct DecompressingWriter<W> {
+    inner: W,
+    dctx: DecompressionContext,
+    buffer: Vec<u8>,
+    header: Vec<u8>,
+    header_done: bool,
+    ended: bool,
+    error: Option<Lz4Error>,
+    opts: DecompressOptions,
+}
+
+#[cfg(feature = "std")]
+impl<W: Writer> DecompressingWriter<W> {
+    /// Creates a new `DecompressingWriter` writing the decompressed data to `dst`, which passes
+    /// `opts` to `decompress`. `buf_size` is the size of the buffer data is decompressed into
+    /// before it is written to `dst`, 64 KB by default. It can't be 0.
+    pub fn new(dst: W,
+               buf_size: Option<usize>,
+               opts: Option<DecompressOptions>) -> Lz4Result<DecompressingWriter<W>> {
+        let size: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
+        if size == 0 {
+            return Err(Lz4Error { desc: "buffer size must not be 0".to_string() });
+        }
+        let ctx: DecompressionContext = try!(create_decompression_context());
+        let mut opts: DecompressOptions = opts.unwrap_or_else(Default::default);
+        // the buffer is reused for every write, so lz4frame has to keep its own copy
+        opts.frame = Default::default();
+        Ok(DecompressingWriter {
+            inner: dst,
+            dctx: ctx,
+            buffer: repeat(0u8).take(size).collect(),
+            header: Vec::new(),
+            header_done: false,
+            ended: false,
+            error: None,
+            opts: opts,
+        })
+    }
+
+    /// Unwraps this `DecompressingWriter`, returning the inner `Writer` and the `FrameStatus` of
+    /// the frame written to it: whether it was complete, ended early, failed its content checksum
+    /// or was invalid otherwise.
+    pub fn finish(self) -> (W, FrameStatus) {
+        // decompression stops at the first error, so the context still knows what caused it
+        let checksum_failed: bool = self.dctx.content_checksum_failed();
+        let status: FrameStatus = match self.error {
+            Some(_) if checksum_failed => FrameStatus::ChecksumMismatch,
+            Some(e) => FrameStatus::Invalid(e.desc),
+            None if !self.ended => FrameStatus::Incomplete,
+            None => FrameStatus::Complete,
+        };
+        (self.inner, status)
+    }
+
+    /// Decompresses all of `src`, writing the decompressed data to the inner `Writer`.
+    fn decompress_all(&mut self, src: &[u8]) -> IoResult<()> {
+        let mut src_offset: usize = 0;
+        while src_offset < src.len() {
+            if self.ended {
+                return Err(decompress_error("data past the end of the frame".to_string()));
+            }
+            match decompress(&mut self.dctx,
+                             self.buffer.as_mut_slice(),
+                             src.slice_from(src_offset),
+                             Some(&self.opts)) {
+                Ok((0, 0, _)) => {
+                    let desc: String = "decompression made no progress".to_string();
+                    self.error = Some(Lz4Error { desc: desc.clone() });
+                    return Err(decompress_error(desc));
+                },
+                Ok((consumed, written, hint)) => {
+                    src_offset += consumed;
+                    try!(self.inner.write(self.buffer.slice_to(written)));
+                    if hint == 0 { self.ended = true; }
+                },
+                Err(lz4err) => {
+                    let desc: String = lz4err.desc.clone();
+                    self.error = Some(lz4err);
+                    return Err(decompress_error(desc));
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Writer> Writer for DecompressingWriter<W> {
+    /// Implementation of `write` for `DecompressingWriter`. Decompresses `buf` as the next part of
+    /// the frame and writes the decompressed data to the inner `Writer`. The frame header is
+    /// collected until it is complete, so that lz4frame gets it in one piece. Fails once the frame
+    /// has turned out to be invalid, and for data past the end of the frame.
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        if let Some(ref e) = self.error {
+            return Err(decompress_error(e.desc.clone()));
+        }
+
+        let mut src: &[u8] = buf;
+        while !self.header_done && src.len() > 0 {
+            let size: usize = required_header_size(self.header.as_slice());
+            let take: usize = min(size - self.header.len(), src.len());
+            self.header.push_all(src.slice_to(take));
+            src = src.slice_from(take);
+            if self.header.len() == required_header_size(self.header.as_slice()) {
+                self.header_done = true;
+                let header: Vec<u8> = mem::replace(&mut self.header, Vec::new());
+                try!(self.decompress_all(header.as_slice()));
+            }
+        }
+        self.decompress_all(src)
+    }
+
+    /// Flushes the inner `Writer`. Decompressed data is written to it as soon as lz4frame returns
+    /// it, so there's nothing else to flush.
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
 /// Convenient function to decompress a file at the given path `src` to the file at the path `dst`
+/// Every frame in `src` is decompressed, one after another, as the lz4 command line tool does.
 /// Returns size of decompressed file or an IoError if something failed during decompression.
+#[cfg(feature = "std")]
 pub fn decompress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoResult<usize> {
+    let mut src_file = try!(File::open(src));
+    let src_size: u64 = try!(src_file.stat()).size;
+    let mut dst_file = try!(File::create(dst));
+
+    let size: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
+    let mut buf: Vec<u8> = repeat(0u8).take(size).collect();
+    loop {
+        let mut decompressor: Decompressor<File> = match Decompressor::new(src_file, None) {
+            Ok(d) => d,
+            Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+        };
+        loop {
+            let bytes_decompressed: usize = match decompressor.read(buf.as_mut_slice()) {
+                Ok(n) => n,
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+                Err(e) => { return Err(e); },
+            };
+            try!(dst_file.write(buf.slice_to(bytes_decompressed)));
+        }
+        // continue right after the frame, in case another one follows
+        src_file = try!(decompressor.into_inner_rewound());
+        if try!(src_file.tell()) >= src_size { break; }
+    }
+    let fstat = try!(dst_file.stat());
+    Ok(fstat.size as usize)
+}
 
+/// Decompresses the file at `src` into the file at `dst` like `decompress_file`, but maps `src`
+/// into memory. When the first frame declares its content size, `dst` is created with that size up
+/// front and mapped as well, so the whole frame is decompressed straight into the mapping with a
+/// single call to `decompress`. Any frames after it are decompressed into `dst` behind it.
+/// Otherwise this falls back to `decompress_file`.
+/// The declared size is only trusted as far as the size of `src` allows, and `dst` is removed
+/// again if decompression fails.
+/// Returns the size of the decompressed file or an IoError if something failed during
+/// decompression.
+#[cfg(all(feature = "mmap", unix))]
+pub fn decompress_file_mmap(src: &Path, dst: &Path) -> IoResult<usize> {
     let src_file = try!(File::open(src));
-    let mut dst_file = try!(File::create(dst));
+    let src_size: usize = try!(src_file.stat()).size as usize;
+    // empty files can't be mapped
+    if src_size == 0 {
+        return decompress_file(src, dst, None);
+    }
 
-    match Decompressor::new(src_file, None) {
-        Ok(ref mut compressed) => {
-            let size: usize = buf_size.unwrap_or(DEFAULT_BUF_SIZE);
-            let mut buf: Vec<u8> = Vec::with_capacity(size);
-            unsafe { buf.set_len(size) };
-            loop {
-                let bytes_decompressed: usize = match compressed.read(buf.as_mut_slice()) {
-                    Ok(n) => n,
-                    Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
-                    Err(e) => { return Err(e); },
-                };
-                try!(dst_file.write(buf.slice_to(bytes_decompressed)));
-            }
-            let fstat = try!(dst_file.stat());
-            Ok(fstat.size as usize)
-        },
-        Err(lz4err) => {
-            return Err(IoError {
-                kind: IoErrorKind::OtherIoError,
-                desc: "lz4 decompress error",
-                detail: Some(lz4err.desc),
-            });
+    let src_map: MemoryMap = try!(map_file(&src_file, src_size, false));
+    let src_ptr: *const u8 = src_map.data() as *const u8;
+    let src_buf: &[u8] = unsafe { slice::from_raw_buf(&src_ptr, src_size) };
+
+    let mut dctx: DecompressionContext = match create_decompression_context() {
+        Ok(ctx) => ctx,
+        Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+    };
+    let (content_size, header_size): (u64, usize) = match get_frame_info(&mut dctx, src_buf) {
+        Ok((finfo, size, _)) => (finfo.content_size, size),
+        Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+    };
+    if content_size == 0 {
+        return decompress_file(src, dst, None);
+    }
+    // a few bytes of header can declare any size, so check it before creating `dst` that large
+    if content_size > src_size as u64 * MAX_RATIO || content_size > usize::MAX as u64 {
+        return Err(decompress_error(format!("frame declared {} bytes of content, more than {} \
+                                             compressed bytes can hold", content_size, src_size)));
+    }
+
+    let mut dst_file = try!(File::open_mode(dst, FileMode::Truncate, FileAccess::ReadWrite));
+    match decompress_mapped(&mut dctx, src_buf, header_size, content_size as usize, &mut dst_file) {
+        Ok(written) => Ok(written),
+        Err(e) => {
+            drop(dst_file);
+            let _ = fs::unlink(dst);
+            Err(e)
         },
     }
+}
+
+/// Decompresses the frame at the start of `src`, whose header of `header_size` bytes `dctx` has
+/// already decoded, straight into `dst_file` mapped at the `content_size` bytes the frame
+/// declares. The frames following it are decompressed into `dst_file` after that.
+#[cfg(all(feature = "mmap", unix))]
+fn decompress_mapped(dctx: &mut DecompressionContext,
+                     src: &[u8],
+                     header_size: usize,
+                     content_size: usize,
+                     dst_file: &mut File) -> IoResult<usize> {
+    try!(dst_file.truncate(content_size as i64));
+    let frame_size: usize = {
+        let dst_map: MemoryMap = try!(map_file(dst_file, content_size, true));
+        let dst_ptr: *mut u8 = dst_map.data();
+        let dst_buf: &mut [u8] = unsafe { slice::from_raw_mut_buf(&dst_ptr, content_size) };
+
+        // the whole frame is decompressed into the mapping, which outlives the call
+        let opts: DecompressOptions = unsafe { DecompressOptions::new(true) };
+        let (consumed, written, hint) = match decompress(dctx,
+                                                         dst_buf,
+                                                         src.slice_from(header_size),
+                                                         Some(&opts)) {
+            Ok(result) => result,
+            Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+        };
+        if hint != 0 || written != content_size {
+            return Err(decompress_error(format!("frame declared {} bytes of content, got {}",
+                                                content_size, written)));
+        }
+        header_size + consumed
+    };
 
+    try!(dst_file.seek(content_size as i64, SeekStyle::SeekSet));
+    let rest: usize = try!(decompress_frames(dctx, src.slice_from(frame_size), dst_file));
+    Ok(content_size + rest)
 }
 
+/// Decompresses the frames in `src` one after another with `dctx`, writing the decompressed data
+/// to `dst`. Returns how many bytes were written.
+#[cfg(all(feature = "mmap", unix))]
+fn decompress_frames<W: Writer>(dctx: &mut DecompressionContext,
+                                src: &[u8],
+                                dst: &mut W) -> IoResult<usize> {
+    let mut buf: Vec<u8> = repeat(0u8).take(DEFAULT_BUF_SIZE).collect();
+    let mut src_offset: usize = 0;
+    let mut total: usize = 0;
+    let mut hint: usize = 0;
+    while src_offset < src.len() {
+        let (consumed, written, next) = match decompress(dctx,
+                                                         buf.as_mut_slice(),
+                                                         src.slice_from(src_offset),
+                                                         None) {
+            Ok(result) => result,
+            Err(lz4err) => { return Err(decompress_error(lz4err.desc)); },
+        };
+        if consumed == 0 && written == 0 {
+            return Err(decompress_error("decompression made no progress".to_string()));
+        }
+        try!(dst.write(buf.slice_to(written)));
+        src_offset += consumed;
+        total += written;
+        hint = next;
+    }
+    if hint != 0 {
+        return Err(decompress_error(truncated_frame().desc));
+    }
+    Ok(total)
+}
+
+/// Wraps a description of a failed decompression in an `IoError`.
+#[cfg(feature = "std")]
+fn decompress_error(detail: String) -> IoError {
+    IoError {
+        kind: IoErrorKind::OtherIoError,
+        desc: "lz4 decompress error",
+        detail: Some(detail),
+    }
+}
+
+
+/***********************************
+ * Simple decompression function
+ * *********************************/
+
+/// How much previously decompressed data blocks of a frame with linked blocks may refer back to.
+const WINDOW_SIZE: usize = 64 * 1024;
+
+/// Decompresses the frame at the start of `src` straight into `dst`, without any buffers of its
+/// own and without allocating, so `dst` has to be large enough for the whole content. If the
+/// frame declares its content size, it is checked against `dst.len()` before anything is
+/// decompressed. Skippable frames in front of the frame are skipped, and anything after the
+/// frame's end is ignored. Block and content checksums are verified. A frame naming a dictionary
+/// is refused.
+/// Of `opts`, only `legacy_header_checksum` makes a difference, to read frames Kafka wrote before
+/// message format v1.
+/// The result is either the number of bytes written into `dst` or an `Lz4Error`.
+pub fn decompress_into(src: &[u8],
+                       dst: &mut [u8],
+                       opts: Option<&DecompressOptions>) -> Lz4Result<usize> {
+    let legacy: bool = opts.map_or(false, |o| o.legacy_header_checksum);
+    let mut ip: usize = 0;
+    let mut header: FrameHeader;
+    loop {
+        let (h, size) = match try!(read_header(src.slice_from(ip), legacy)) {
+            Some(h) => h,
+            None => { return Err(truncated_frame()); },
+        };
+        header = h;
+        ip += size;
+        if header.info.frame_type != FrameType::SkippableFrame { break; }
+        if src.len() - ip < header.skip_size { return Err(truncated_frame()); }
+        ip += header.skip_size;
+    }
+    if let Some(dict_id) = header.dict_id {
+        return Err(dictionary_not_given(dict_id));
+    }
+
+    let content_size: u64 = header.info.content_size;
+    if content_size > dst.len() as u64 {
+        return Err(Lz4Error {
+            desc: format!("frame declares {} bytes of content, destination holds {}",
+                          content_size, dst.len()),
+        });
+    }
+
+    let block_size: usize = header.info.block_size_id.bytes();
+    let linked: bool = header.info.block_mode == BlockMode::Linked;
+    let mut checksum: Option<Xxh32> = match header.info.content_checksum_flag {
+        ContentChecksum::Enabled => Some(Xxh32::new(0)),
+        ContentChecksum::Disabled => None,
+    };
+    let mut op: usize = 0;
+    let mut blocks: u64 = 0;
+
+    loop {
+        if src.len() - ip < BLOCK_HEADER_SIZE { return Err(truncated_frame()); }
+        let value: u32 = read_u32(src, ip);
+        ip += BLOCK_HEADER_SIZE;
+        if value == 0 { break; }
+
+        let size: usize = (value & !UNCOMPRESSED_FLAG) as usize;
+        if size > block_size {
+            return Err(Lz4Error { desc: "block larger than maximum block size".to_string() });
+        }
+        let needed: usize = size + if header.block_checksum { CHECKSUM_SIZE } else { 0 };
+        if src.len() - ip < needed { return Err(truncated_frame()); }
+        let data: &[u8] = src.slice(ip, ip + size);
+        if header.block_checksum && read_u32(src, ip + size) != xxh32(data, 0) {
+            return Err(block_checksum_mismatch(blocks));
+        }
+        ip += needed;
+        blocks += 1;
+
+        let end: usize = min(op + block_size, dst.len());
+        let len: usize = if value & UNCOMPRESSED_FLAG != 0 {
+            if size > end - op { return Err(dst_too_small()); }
+            copy_memory(dst.slice_mut(op, op + size), data);
+            size
+        } else if linked {
+            let prefix: usize = min(op, WINDOW_SIZE);
+            try!(block::decompress_with_prefix(data, dst.slice_mut(op - prefix, end), prefix))
+        } else {
+            try!(block::decompress(data, dst.slice_mut(op, end)))
+        };
+
+        if let Some(ref mut hasher) = checksum {
+            hasher.update(dst.slice(op, op + len));
+        }
+        op += len;
+    }
+
+    if let Some(ref hasher) = checksum {
+        if src.len() - ip < CHECKSUM_SIZE { return Err(truncated_frame()); }
+        if read_u32(src, ip) != hasher.digest() {
+            return Err(content_checksum_mismatch());
+        }
+    }
+    if content_size != 0 && content_size != op as u64 {
+        return Err(Lz4Error {
+            desc: format!("frame declared {} bytes of content, got {}", content_size, op),
+        });
+    }
+    Ok(op)
+}
+
+fn truncated_frame() -> Lz4Error {
+    Lz4Error { desc: "frame truncated".to_string() }
+}
+
+fn dst_too_small() -> Lz4Error {
+    Lz4Error { desc: "destination too small to hold decompressed frame".to_string() }
+}
 
 /***********************************
  * Decompression functions
@@ -187,89 +847,414 @@ pub fn decompress_file(src: &Path, dst: &Path, buf_size: Option<usize>) -> IoRes
 
 /* Resource management */
 
+/// An lz4frame decompression context, which decodes one frame at a time through `frame_info()`
+/// and `decompress()`. After a frame is fully decoded, it can be used again for another frame.
+#[cfg(not(feature = "pure-rust"))]
+pub struct DecompressionContext {
+    ctx: Context,
+    /// The frame header as far as it has been handed to lz4frame by `decompress()`.
+    header: Vec<u8>,
+    /// Whether the decoded frame header has been looked at, see `decompress_pieces()`.
+    checked: bool,
+    /// Set while decoding a frame with block checksums, see `decompress_pieces()`.
+    blocks: Option<BlockPosition>,
+    in_frame: bool,
+    /// Whether the last `decompress()` call found the content checksum not to match.
+    checksum_failed: bool,
+}
+
+/// Where `DecompressionContext::decompress_pieces()` is in the blocks of a frame with block
+/// checksums.
+#[cfg(not(feature = "pure-rust"))]
+struct BlockPosition {
+    /// The current block header as far as it has been handed to lz4frame.
+    header: Vec<u8>,
+    /// How many bytes of the current block's data and checksum are still to be handed over.
+    left: usize,
+    /// Index of the current block in the frame, starting at 0.
+    index: u64,
+    /// Whether the end mark has been handed over, after which only the content checksum follows.
+    ended: bool,
+}
+
+#[cfg(not(feature = "pure-rust"))]
+impl BlockPosition {
+    fn new() -> BlockPosition {
+        BlockPosition { header: Vec::new(), left: 0, index: 0, ended: false }
+    }
+
+    /// How many of the next `len` bytes are left of the current block header or block.
+    fn span(&self, len: usize) -> usize {
+        if self.ended {
+            len
+        } else if self.header.len() < BLOCK_HEADER_SIZE {
+            min(len, BLOCK_HEADER_SIZE - self.header.len())
+        } else {
+            min(len, self.left)
+        }
+    }
+
+    /// Moves past `src`, which lz4frame has taken, and which is at most `span()` bytes long.
+    fn advance(&mut self, src: &[u8]) {
+        if self.ended || src.len() == 0 {
+            return;
+        }
+        if self.header.len() < BLOCK_HEADER_SIZE {
+            self.header.push_all(src);
+            if self.header.len() == BLOCK_HEADER_SIZE {
+                let value: u32 = read_u32(self.header.as_slice(), 0);
+                if value == 0 {
+                    self.ended = true;
+                } else {
+                    self.left = (value & !UNCOMPRESSED_FLAG) as usize + CHECKSUM_SIZE;
+                }
+            }
+        } else {
+            self.left -= src.len();
+            if self.left == 0 {
+                self.header.clear();
+                self.index += 1;
+            }
+        }
+    }
+}
+
+/// A frame decompression context, which decodes one frame at a time through `frame_info()` and
+/// `decompress()`. With the `pure-rust` feature, the frame is decoded by a `FrameDecoder`.
+#[cfg(feature = "pure-rust")]
+pub struct DecompressionContext {
+    decoder: FrameDecoder,
+    in_frame: bool,
+}
+
+/// See the `frame` module docs on sending contexts to other threads.
+#[cfg(not(feature = "pure-rust"))]
+unsafe impl Send for DecompressionContext {}
+
+#[cfg(not(feature = "pure-rust"))]
+impl DecompressionContext {
+    /// wrapper around `LZ4F_createDecompressionContext()`
+    pub fn new() -> Lz4Result<DecompressionContext> {
+        let mut ctx: Context = ptr::null_mut();
+        let err = unsafe { LZ4F_createDecompressionContext(&mut ctx, LZ4F_VERSION) };
+        try!(maybe_error(err));
+        Ok(DecompressionContext {
+            ctx: ctx,
+            header: Vec::new(),
+            checked: false,
+            blocks: None,
+            in_frame: false,
+            checksum_failed: false,
+        })
+    }
+
+    /// Decodes the frame header at the start of `src`, without starting decompression. Returns the
+    /// `FrameInfo`, the number of bytes read from `src` (0 if the header had already been decoded)
+    /// and a hint of how many bytes `decompress()` expects next.
+    pub fn frame_info(&mut self, src: &[u8]) -> Lz4Result<(FrameInfo, usize, usize)> {
+        let mut finfo: FrameInfo = Default::default();
+        let src_ptr: *const c_void = src.as_ptr() as *const c_void;
+        let mut src_size_t: size_t = src.len() as size_t;
+        let err = unsafe {
+            LZ4F_getFrameInfo(self.ctx, &mut finfo, src_ptr, &mut src_size_t)
+        };
+        let hint: usize = try!(maybe_error(err));
+        debug_assert!(src_size_t as usize <= src.len());
+        if src_size_t != 0 { self.in_frame = true; }
+        Ok((finfo, src_size_t as usize, hint))
+    }
+
+    /// Decompresses as much of `src` into `dst` as fits. Returns the number of bytes read from
+    /// `src`, the number of bytes written into `dst` and a hint of how many bytes are expected
+    /// next, which is 0 once the frame is fully decoded.
+    /// With `legacy_header_checksum` set in `opts`, the frame header has to be passed whole. A
+    /// block whose checksum doesn't match is reported by its index in the frame. A frame naming a
+    /// dictionary fails after its header, since it can't be decoded without the dictionary.
+    pub fn decompress(&mut self,
+                      dst: &mut [u8],
+                      src: &[u8],
+                      opts: Option<&DecompressOptions>) -> Lz4Result<(usize, usize, usize)> {
+        let def_opts: DecompressOptions = Default::default();
+        let opts: &DecompressOptions = match opts {
+            Some(o) => o,
+            None => &def_opts,
+        };
+        self.checksum_failed = false;
+
+        let mut header_size: usize = 0;
+        if opts.legacy_header_checksum && !self.header_decoded() {
+            header_size = try!(self.decompress_header(src, opts));
+        }
+
+        let src: &[u8] = src.slice_from(header_size);
+        let (consumed, written, hint) = match self.decompress_pieces(dst, src, &opts.frame) {
+            Ok(result) => result,
+            Err(lz4err) => {
+                // lz4frame is left in the middle of the frame
+                self.in_frame = true;
+                return Err(lz4err);
+            },
+        };
+        if hint == 0 {
+            self.header.clear();
+            self.checked = false;
+            self.blocks = None;
+            self.in_frame = false;
+        } else if consumed != 0 || header_size != 0 {
+            self.in_frame = true;
+        }
+        Ok((consumed + header_size, written, hint))
+    }
+
+    /// Hands `src` to lz4frame in pieces, which it decodes and checks as usual: first the frame
+    /// header on its own, so that a frame naming a dictionary is refused before any of its blocks
+    /// is decoded, and then, in a frame with block checksums, each block header and block on its
+    /// own, so that a checksum lz4frame finds not to match is put down to its block.
+    fn decompress_pieces(&mut self,
+                         dst: &mut [u8],
+                         src: &[u8],
+                         opts: &FrameDecompressOptions) -> Lz4Result<(usize, usize, usize)> {
+        let mut ip: usize = 0;
+        let mut op: usize = 0;
+        loop {
+            if !self.checked && self.header_decoded() {
+                let (finfo, _, _) = try!(self.frame_info(&[]));
+                if let Some(dict_id) = finfo.dictionary_id() {
+                    return Err(dictionary_not_given(dict_id));
+                }
+                if finfo.frame_type != FrameType::SkippableFrame &&
+                   finfo.block_checksum_flag == BlockChecksum::Enabled {
+                    self.blocks = Some(BlockPosition::new());
+                }
+                self.checked = true;
+            }
+
+            let rest: usize = src.len() - ip;
+            let (len, index): (usize, u64) = if !self.checked {
+                let size: usize = required_header_size(self.header.as_slice());
+                (min(rest, size - self.header.len()), 0)
+            } else {
+                match self.blocks {
+                    Some(ref blocks) => (blocks.span(rest), blocks.index),
+                    None => (rest, 0),
+                }
+            };
+            let piece: &[u8] = src.slice(ip, ip + len);
+            let (consumed, written, hint) = try!(self.lz4f_decompress(dst.slice_from_mut(op),
+                                                                      piece,
+                                                                      opts,
+                                                                      index));
+            if !self.checked {
+                self.header.push_all(piece.slice_to(consumed));
+            } else if let Some(ref mut blocks) = self.blocks {
+                blocks.advance(piece.slice_to(consumed));
+            }
+            ip += consumed;
+            op += written;
+            if hint == 0 || consumed == 0 || consumed < len || ip == src.len() {
+                return Ok((ip, op, hint));
+            }
+        }
+    }
+
+    /// Does nothing: lz4frame keeps no options between `decompress()` calls. It is here for
+    /// `ContextPool`, which resets the options of the Rust implementation's contexts.
+    pub fn reset_options(&mut self) {}
+
+    /// Whether the header of the frame being decompressed has been decoded.
+    fn header_decoded(&mut self) -> bool {
+        self.frame_info(&[]).is_ok()
+    }
+
+    /// Checks the frame header at the start of `src` against Kafka's legacy checksum in Rust, and
+    /// hands lz4frame the same header with the standard checksum instead. Returns the size of the
+    /// header, or 0 if `src` is empty or starts a skippable frame, which lz4frame can take as it
+    /// is.
+    fn decompress_header(&mut self,
+                         src: &[u8],
+                         opts: &DecompressOptions) -> Lz4Result<usize> {
+        if src.len() == 0 {
+            return Ok(0);
+        }
+        let size: usize = match try!(read_header(src, true)) {
+            Some((ref header, _)) if header.info.frame_type == FrameType::SkippableFrame => {
+                return Ok(0);
+            },
+            Some((_, size)) => size,
+            None => {
+                return Err(Lz4Error {
+                    desc: "frame header with legacy checksum has to be passed whole".to_string(),
+                });
+            },
+        };
+        let mut header: Vec<u8> = src.slice_to(size).to_vec();
+        rewrite_header_checksum(header.as_mut_slice(), false);
+
+        let mut empty: [u8; 0] = [];
+        try!(self.lz4f_decompress(&mut empty, header.as_slice(), &opts.frame, 0));
+        Ok(size)
+    }
+
+    /// Calls `LZ4F_decompress()` on this context, decompressing as much of `src` into `dst` as
+    /// fits. Returns the number of bytes read from `src`, the number of bytes written into `dst`
+    /// and lz4frame's hint of how many bytes it expects next. Checksum mismatches are reported the
+    /// way the Rust implementation reports them, a block checksum mismatch as one in block
+    /// `block_index`, and a content checksum mismatch is recorded for `content_checksum_failed()`.
+    fn lz4f_decompress(&mut self,
+                       dst: &mut [u8],
+                       src: &[u8],
+                       opts: &FrameDecompressOptions,
+                       block_index: u64) -> Lz4Result<(usize, usize, usize)> {
+        let mut dst_size_t: size_t = dst.len() as size_t;
+        let mut src_size_t: size_t = src.len() as size_t;
+        let dst_ptr: *mut c_void = dst.as_mut_ptr() as *mut c_void;
+        let src_ptr: *const c_void = src.as_ptr() as *const c_void;
+        let err = unsafe {
+            LZ4F_decompress(self.ctx, dst_ptr, &mut dst_size_t, src_ptr, &mut src_size_t, opts)
+        };
+        if err == CONTENT_CHECKSUM_INVALID {
+            self.checksum_failed = true;
+            return Err(content_checksum_mismatch());
+        }
+        if err == BLOCK_CHECKSUM_INVALID {
+            return Err(block_checksum_mismatch(block_index));
+        }
+        let hint: usize = try!(maybe_error(err));
+        debug_assert!(src_size_t as usize <= src.len() && dst_size_t as usize <= dst.len());
+        Ok((src_size_t as usize, dst_size_t as usize, hint))
+    }
+
+    /// Whether the last `decompress()` call failed because the frame's content checksum didn't
+    /// match its content.
+    pub fn content_checksum_failed(&self) -> bool {
+        self.checksum_failed
+    }
+}
+
+/// Implements drop to ensure the underlying context is free'd properly.
+#[cfg(not(feature = "pure-rust"))]
+impl Drop for DecompressionContext {
+    fn drop(&mut self) {
+        unsafe { LZ4F_freeDecompressionContext(self.ctx); }
+    }
+}
+
+#[cfg(feature = "pure-rust")]
+impl DecompressionContext {
+    /// Creates a new `DecompressionContext`.
+    pub fn new() -> Lz4Result<DecompressionContext> {
+        Ok(DecompressionContext { decoder: FrameDecoder::new(), in_frame: false })
+    }
+
+    /// Decodes the frame header at the start of `src`, without starting decompression. Returns the
+    /// `FrameInfo`, the number of bytes read from `src` (0 if the header had already been decoded)
+    /// and a hint of how many bytes `decompress()` expects next.
+    pub fn frame_info(&mut self, src: &[u8]) -> Lz4Result<(FrameInfo, usize, usize)> {
+        let (finfo, size, hint) = try!(self.decoder.frame_info(src));
+        if size != 0 { self.in_frame = true; }
+        Ok((finfo, size, hint))
+    }
+
+    /// Decompresses as much of `src` into `dst` as fits. Returns the number of bytes read from
+    /// `src`, the number of bytes written into `dst` and a hint of how many bytes are expected
+    /// next, which is 0 once the frame is fully decoded.
+    /// Of `opts`, only `legacy_header_checksum` makes a difference to the Rust implementation.
+    pub fn decompress(&mut self,
+                      dst: &mut [u8],
+                      src: &[u8],
+                      opts: Option<&DecompressOptions>) -> Lz4Result<(usize, usize, usize)> {
+        if let Some(opts) = opts {
+            self.decoder.set_legacy_header_checksum(opts.legacy_header_checksum);
+        }
+        match self.decoder.decompress(dst, src) {
+            Ok((consumed, written, hint)) => {
+                if hint == 0 {
+                    self.in_frame = false;
+                } else if consumed != 0 {
+                    self.in_frame = true;
+                }
+                Ok((consumed, written, hint))
+            },
+            Err(lz4err) => {
+                // the decoder is left in the middle of the frame
+                self.in_frame = true;
+                Err(lz4err)
+            },
+        }
+    }
+
+    /// Forgets the `legacy_header_checksum` setting of earlier `decompress()` calls, which the
+    /// decoder keeps for calls without `opts`. `ContextPool` does this when a context is put back,
+    /// so that its next user starts from the defaults.
+    pub fn reset_options(&mut self) {
+        self.decoder.set_legacy_header_checksum(false);
+    }
+
+    /// Whether the last `decompress()` call failed because the frame's content checksum didn't
+    /// match its content.
+    pub fn content_checksum_failed(&self) -> bool {
+        self.decoder.content_checksum_failed()
+    }
+}
+
+impl DecompressionContext {
+    /// Whether decoding of a frame has started and the frame isn't fully decoded yet. A context
+    /// can only start on a new frame once it has fully decoded the last one, which is why
+    /// `ContextPool` drops contexts handed back in the middle of a frame.
+    pub fn in_frame(&self) -> bool {
+        self.in_frame
+    }
+}
+
 /// wrapper around `LZ4F_createDecompressionContext()`
-pub fn create_decompression_context() -> Lz4Result<Lz4FrameContext> {
-    Lz4FrameContext::new(FrameContextType::Decompression)
+pub fn create_decompression_context() -> Lz4Result<DecompressionContext> {
+    DecompressionContext::new()
 }
 
 /// This function decodes frame header information, such as `block_size`. It is optional, you could
 /// start by calling `decompress()` directly instead. The objective is to extract header information
 /// without starting decompression, typically for allocation purposes.
-/// The function will work only if `src_buf` points at the beginning of the frame and `src_size` is
-/// large enough to decode the whole header (typically, between 7 and 15 bytes).
-/// The number of bytes read from `src_buf` will be provided within `src_size` (necessarily <=
-/// original value). It is basically the frame header size.
-/// You are expected to resume decompression from where it stopped (`src_buff` + `src_size`)
-/// 
-/// The function result is either a tuple of the decoded `Some(FrameInfo)` and a hint of how many
-/// `src_size` bytes `decompress()` expects for the next call, or `None` and an `Lz4Error`.
-pub fn get_frame_info(dctx: &mut Lz4FrameContext,
-                      src_buf: &[u8],
-                      src_size: &mut usize) -> (Option<FrameInfo>, Lz4Result<usize>) {
-    let mut finfo: FrameInfo = Default::default();
-    let src_ptr: *const c_void = src_buf.as_ptr() as *const c_void;
-    let mut src_size_t: size_t = src_size.clone() as size_t;
-    let err = unsafe {
-        LZ4F_getFrameInfo(dctx.ctx, &mut finfo, src_ptr, &mut src_size_t)
-    };
-    *src_size = src_size_t as usize;
-    if is_error(err) {
-        (None, maybe_error(err))
-    } else {
-        (Some(finfo), maybe_error(err))
-    }
+/// The function will work only if `src_buf` starts at the beginning of the frame and is large
+/// enough to hold the whole header (typically, between 7 and 19 bytes).
+/// You are expected to resume decompression from where it stopped.
+///
+/// The function result is either a tuple of the decoded `FrameInfo`, the number of bytes read from
+/// `src_buf` and a hint of how many bytes `decompress()` expects for the next call, or an
+/// `Lz4Error`. The number of bytes read is basically the frame header size, or 0 if the header had
+/// already been decoded by an earlier call.
+pub fn get_frame_info(dctx: &mut DecompressionContext,
+                      src_buf: &[u8]) -> Lz4Result<(FrameInfo, usize, usize)> {
+    dctx.frame_info(src_buf)
 }
 
 /// Call this function repetitively to regenerate data compressed within `src_buf`. The function
-/// will attempt to decode `src_size` bytes from `src_buf` into `dst_buf` of maximum size `dst_size`
+/// will attempt to decode all of `src_buf` into `dst_buf`.
 ///
-/// The number of bytes regenerated into `dst_buf` will be provided within `dst_size` (necessarily
-/// <= original value).
-/// The number of bytes read from `src_buf` will be provided within `src_size` (necessarily, <=
-/// original value).
+/// The function result is a tuple of the number of bytes read from `src_buf`, the number of bytes
+/// regenerated into `dst_buf` and a hint of how many bytes `decompress()` expects for the next
+/// call, or an `Lz4Error` if decompression failed.
 /// If number of bytes read is < number of bytes provided, then decomrpession operation is not
 /// completed. It typically happens when `dst_buf` is not large enough to contain all decoded data.
-/// `decompress()` must be called again, starting from where it stopped (`src_buf` + `src_size`).
-/// The underlying `LZ4F_decompress()` function will check this condition and refuse to continue
-/// if it is not respected.
+/// `decompress()` must be called again, starting from where it stopped.
 ///
 /// `dst_buf` is supposed to be flushed between each call to the function, since its content will be
 /// overweitten. `dst*` options can be changed at will with each consecutive call to the function.
 ///
-/// The function result is a hint of how many `src_size` bytes `decompress()` expects for the next
-/// call. Schematically, it's the size of the current (or remaining) compressed block + header of 
+/// Schematically, the hint is the size of the current (or remaining) compressed block + header of
 /// the next block. Respecting the hing provides some boost to performance, since it does skip
-/// intermediate buffers. This is just a hint though, you can always provide any `src_size` you
-/// want.
-/// When a frame is fully decoded, the function result will be 0 (no more data expected).
-/// If decompression failed, the result will be an `Lz4Error`.
+/// intermediate buffers. This is just a hint though, you can always provide any number of bytes
+/// you want.
+/// When a frame is fully decoded, the hint will be 0 (no more data expected).
 ///
 /// After a frame is fully decoded, `dctx` can be used again to decompress another frame.
-pub fn decompress(dctx: &mut Lz4FrameContext,
+///
+/// lz4frame rejects the header checksum Kafka wrote before message format v1. With
+/// `legacy_header_checksum` set in `decompress_opts`, the frame header is checked in Rust instead,
+/// so it has to be passed to `decompress()` whole, in a single call.
+pub fn decompress(dctx: &mut DecompressionContext,
                   dst_buf: &mut [u8],
-                  dst_size: &mut usize,
                   src_buf: &[u8],
-                  src_size: &mut usize,
-                  decompress_opts: Option<&FrameDecompressOptions>) -> Lz4Result<usize> {
-
-    let def_opts: FrameDecompressOptions = Default::default();
-    let opts: &FrameDecompressOptions = match decompress_opts {
-        Some(o) => o,
-        None => &def_opts,
-    };
-
-    let mut dst_size_t: size_t = dst_size.clone() as size_t;
-    let mut src_size_t: size_t = src_size.clone() as size_t;
-
-    let dst_ptr: *mut c_void = dst_buf.as_mut_ptr() as *mut c_void;
-    let src_ptr: *const c_void = src_buf.as_ptr() as *const c_void;
-
-    let err = unsafe {
-        LZ4F_decompress(dctx.ctx, dst_ptr, &mut dst_size_t, src_ptr, &mut src_size_t, opts)
-    };
-
-    *dst_size = dst_size_t as usize;
-    *src_size = src_size_t as usize;
-
-    maybe_error(err)
+                  decompress_opts: Option<&DecompressOptions>)
+                  -> Lz4Result<(usize, usize, usize)> {
+    dctx.decompress(dst_buf, src_buf, decompress_opts)
 }
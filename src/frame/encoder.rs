@@ -0,0 +1,277 @@
+//! A Rust implementation of lz4frame's compression functions, used by the `Compressor` and the
+//! wrappers in `compress` when the `pure-rust` feature is enabled, and exported as `frame::rust`.
+//! Blocks are always compressed independently of each other, whatever the preferences' block mode
+//! is, and compression levels are ignored since there is no Rust implementation of LZ4 HC.
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::default::Default;
+use std::slice::bytes::copy_memory;
+
+use lz4rs_sys::{
+    FramePreferences,
+    FrameInfo,
+    BlockMode,
+    ContentChecksum,
+    BlockChecksum,
+};
+
+use super::super::core::block;
+use super::super::xxhash::{xxh32, Xxh32};
+
+use super::header::{
+    MAX_HEADER_SIZE,
+    BLOCK_HEADER_SIZE,
+    END_MARK_SIZE,
+    CHECKSUM_SIZE,
+    UNCOMPRESSED_FLAG,
+    write_header,
+    dictionary_not_given,
+    write_u32,
+};
+
+use super::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+/// Compression state of a single frame, the counterpart of an `LZ4F_compressionContext_t`.
+pub struct FrameEncoder {
+    started: bool,
+    block_size: usize,
+    auto_flush: bool,
+    block_checksum: bool,
+    content_checksum: Option<Xxh32>,
+    content_size: u64,
+    total_in: u64,
+    buffer: Vec<u8>,
+}
+
+impl FrameEncoder {
+    /// Creates a `FrameEncoder`. `begin` has to be called before anything can be compressed.
+    pub fn new() -> FrameEncoder {
+        FrameEncoder {
+            started: false,
+            block_size: 0,
+            auto_flush: false,
+            block_checksum: false,
+            content_checksum: None,
+            content_size: 0,
+            total_in: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Starts a new frame according to `prefs`, writing its header into `dst`. Returns the size of
+    /// the header. Fails if `prefs` name a dictionary ID, since there's no dictionary to compress
+    /// with and the frame couldn't be decompressed.
+    pub fn begin(&mut self, dst: &mut [u8], prefs: &FramePreferences) -> Lz4Result<usize> {
+        let info: &FrameInfo = &prefs.frame_info;
+        if info.dict_id != 0 {
+            return Err(dictionary_not_given(info.dict_id));
+        }
+        let header: FrameInfo = FrameInfo {
+            block_size_id: info.block_size_id,
+            block_mode: BlockMode::Independent,
+            content_checksum_flag: info.content_checksum_flag,
+            frame_type: info.frame_type,
+            content_size: info.content_size,
+            block_checksum_flag: info.block_checksum_flag,
+            dict_id: 0,
+        };
+        let len: usize = try!(write_header(&header, dst));
+
+        self.started = true;
+        self.block_size = info.block_size_id.bytes();
+        self.auto_flush = prefs.auto_flush != 0;
+        self.block_checksum = info.block_checksum_flag == BlockChecksum::Enabled;
+        self.content_checksum = if info.content_checksum_flag == ContentChecksum::Enabled {
+            Some(Xxh32::new(0))
+        } else {
+            None
+        };
+        self.content_size = info.content_size;
+        self.total_in = 0;
+        self.buffer.clear();
+        Ok(len)
+    }
+
+    /// Compresses `src` into `dst`, returning the number of bytes written. Input that doesn't make
+    /// up a whole block is kept until more input arrives, unless auto flush is enabled.
+    /// `dst` has to be able to hold `compress_bound(src.len())` bytes.
+    pub fn update(&mut self, dst: &mut [u8], src: &[u8]) -> Lz4Result<usize> {
+        try!(self.check_started());
+        if dst.len() < self.bound(src.len()) {
+            return Err(dst_too_small());
+        }
+        if let Some(ref mut hasher) = self.content_checksum {
+            hasher.update(src);
+        }
+        self.total_in += src.len() as u64;
+
+        let mut op: usize = 0;
+        let mut ip: usize = 0;
+
+        // complete the buffered block first
+        if self.buffer.len() > 0 {
+            let wanted: usize = self.block_size - self.buffer.len();
+            if src.len() < wanted {
+                self.buffer.push_all(src);
+                ip = src.len();
+            } else {
+                self.buffer.push_all(src.slice_to(wanted));
+                ip = wanted;
+                op += try!(write_block(dst.slice_from_mut(op), self.buffer.as_slice(),
+                                       self.block_checksum));
+                self.buffer.clear();
+            }
+        }
+
+        // then compress whole blocks straight from `src`
+        while src.len() - ip >= self.block_size {
+            op += try!(write_block(dst.slice_from_mut(op), src.slice(ip, ip + self.block_size),
+                                   self.block_checksum));
+            ip += self.block_size;
+        }
+
+        self.buffer.push_all(src.slice_from(ip));
+        if self.auto_flush {
+            op += try!(self.flush(dst.slice_from_mut(op)));
+        }
+        Ok(op)
+    }
+
+    /// Compresses whatever input is buffered into a block, returning the number of bytes written
+    /// into `dst`, which may be 0 if nothing was buffered.
+    pub fn flush(&mut self, dst: &mut [u8]) -> Lz4Result<usize> {
+        try!(self.check_started());
+        if self.buffer.len() == 0 {
+            return Ok(0);
+        }
+        let len: usize = try!(write_block(dst, self.buffer.as_slice(), self.block_checksum));
+        self.buffer.clear();
+        Ok(len)
+    }
+
+    /// Finishes the frame by flushing buffered input and writing the end mark and the content
+    /// checksum, if enabled. Returns the number of bytes written into `dst`.
+    /// Afterwards, `begin` may be called again to start another frame.
+    pub fn end(&mut self, dst: &mut [u8]) -> Lz4Result<usize> {
+        let mut op: usize = try!(self.flush(dst));
+        self.started = false;
+
+        if self.content_size != 0 && self.content_size != self.total_in {
+            return Err(Lz4Error {
+                desc: format!("frame declared {} bytes of content, got {}",
+                              self.content_size, self.total_in),
+            });
+        }
+
+        let checksum: Option<u32> = self.content_checksum.as_ref().map(|h| h.digest());
+        let trailer: usize = END_MARK_SIZE + if checksum.is_some() { CHECKSUM_SIZE } else { 0 };
+        if dst.len() < op + trailer {
+            return Err(dst_too_small());
+        }
+        write_u32(dst, op, 0);
+        op += END_MARK_SIZE;
+        if let Some(c) = checksum {
+            write_u32(dst, op, c);
+            op += CHECKSUM_SIZE;
+        }
+        Ok(op)
+    }
+
+    /// Worst case output size of an `update` with `src_size` bytes given what's already buffered:
+    /// every whole block stored uncompressed, plus the partial one if auto flush is enabled.
+    fn bound(&self, src_size: usize) -> usize {
+        let checksum_size: usize = if self.block_checksum { CHECKSUM_SIZE } else { 0 };
+        let overhead: usize = BLOCK_HEADER_SIZE + checksum_size;
+        let total: usize = src_size + self.buffer.len();
+        let mut bound: usize = (total / self.block_size) * (overhead + self.block_size);
+        if self.auto_flush && total % self.block_size > 0 {
+            bound += overhead + (total % self.block_size);
+        }
+        bound
+    }
+
+    fn check_started(&self) -> Lz4Result<()> {
+        if self.started {
+            Ok(())
+        } else {
+            Err(Lz4Error { desc: "compression has not been started with begin".to_string() })
+        }
+    }
+}
+
+/// Writes `data` as a single block into `dst`, compressed if that makes it smaller and stored as
+/// is otherwise, followed by the checksum of the stored block with `checksum`. Returns the number
+/// of bytes written.
+fn write_block(dst: &mut [u8], data: &[u8], checksum: bool) -> Lz4Result<usize> {
+    let checksum_size: usize = if checksum { CHECKSUM_SIZE } else { 0 };
+    if dst.len() < BLOCK_HEADER_SIZE + data.len() + checksum_size {
+        return Err(dst_too_small());
+    }
+    let limit: usize = BLOCK_HEADER_SIZE + data.len() - 1;
+    let size: usize = match block::rust::compress(data,
+                                                  dst.slice_mut(BLOCK_HEADER_SIZE, limit),
+                                                  block::DEFAULT_ACCELERATION) {
+        Ok(len) => {
+            write_u32(dst, 0, len as u32);
+            len
+        },
+        // doesn't compress, so store it uncompressed
+        Err(_) => {
+            write_u32(dst, 0, (data.len() as u32) | UNCOMPRESSED_FLAG);
+            copy_memory(dst.slice_mut(BLOCK_HEADER_SIZE, BLOCK_HEADER_SIZE + data.len()), data);
+            data.len()
+        },
+    };
+    let end: usize = BLOCK_HEADER_SIZE + size;
+    if checksum {
+        let hash: u32 = xxh32(dst.slice(BLOCK_HEADER_SIZE, end), 0);
+        write_u32(dst, end, hash);
+    }
+    Ok(end + checksum_size)
+}
+
+fn dst_too_small() -> Lz4Error {
+    Lz4Error { desc: "destination too small".to_string() }
+}
+
+/// Same as `LZ4F_compressBound`: the worst case size of the output of a single `update`, `flush`
+/// and `end` of `src_size` bytes.
+pub fn compress_bound(src_size: usize, prefs: &FramePreferences) -> usize {
+    let block_size: usize = prefs.frame_info.block_size_id.bytes();
+    let nb_blocks: usize = src_size / block_size + 1;
+    let last_block_size: usize = if prefs.auto_flush != 0 { src_size % block_size }
+                                 else { block_size };
+    let block_overhead: usize = BLOCK_HEADER_SIZE +
+        if prefs.frame_info.block_checksum_flag == BlockChecksum::Enabled { CHECKSUM_SIZE }
+        else { 0 };
+    let frame_end: usize = END_MARK_SIZE +
+        if prefs.frame_info.content_checksum_flag == ContentChecksum::Enabled { CHECKSUM_SIZE }
+        else { 0 };
+    (block_overhead * nb_blocks) + (block_size * (nb_blocks - 1)) + last_block_size + frame_end
+}
+
+/// Same as `LZ4F_compressFrameBound`: the worst case size of a whole frame holding `src_size`
+/// bytes.
+pub fn compress_frame_bound(src_size: usize, prefs: &FramePreferences) -> usize {
+    let mut flushing: FramePreferences = Default::default();
+    flushing.frame_info.block_size_id = prefs.frame_info.block_size_id;
+    flushing.frame_info.content_checksum_flag = prefs.frame_info.content_checksum_flag;
+    flushing.frame_info.block_checksum_flag = prefs.frame_info.block_checksum_flag;
+    flushing.auto_flush = 1;
+    MAX_HEADER_SIZE + compress_bound(src_size, &flushing)
+}
+
+/// Compresses `src` into a whole frame in `dst`, returning the size of the frame.
+pub fn compress_frame(dst: &mut [u8], src: &[u8], prefs: &FramePreferences) -> Lz4Result<usize> {
+    let mut encoder: FrameEncoder = FrameEncoder::new();
+    let mut op: usize = try!(encoder.begin(dst, prefs));
+    encoder.auto_flush = true;
+    op += try!(encoder.update(dst.slice_from_mut(op), src));
+    op += try!(encoder.end(dst.slice_from_mut(op)));
+    Ok(op)
+}
@@ -0,0 +1,340 @@
+//! Rust implementation of the frame header of the LZ4 Framing Format: the magic number followed by
+//! the frame descriptor (FLG and BD bytes, optional content size and dictionary ID, and the header
+//! checksum).
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::default::Default;
+
+use lz4rs_sys::{
+    FrameInfo,
+    BlockSize,
+    BlockMode,
+    ContentChecksum,
+    BlockChecksum,
+    FrameType,
+};
+
+use super::super::xxhash::xxh32;
+
+use super::structs::{
+    Lz4Error,
+    Lz4Result,
+};
+
+/// Magic number every LZ4 frame starts with.
+pub const MAGIC_NUMBER: u32 = 0x184D2204;
+
+/// Skippable frames start with any magic number from `SKIPPABLE_MAGIC_NUMBER` to
+/// `SKIPPABLE_MAGIC_NUMBER + 15`, followed by the size of their content.
+pub const SKIPPABLE_MAGIC_NUMBER: u32 = 0x184D2A50;
+
+/// Size of the smallest frame header: magic number, FLG, BD and header checksum.
+pub const MIN_HEADER_SIZE: usize = 7;
+
+/// Size of the largest frame header, which also holds the content size and a dictionary ID.
+pub const MAX_HEADER_SIZE: usize = 19;
+
+/// Size of the header of a skippable frame: magic number and content size.
+pub const SKIPPABLE_HEADER_SIZE: usize = 8;
+
+/// Size of the dictionary ID in a frame descriptor.
+const DICT_ID_SIZE: usize = 4;
+
+/// Size of the header in front of every block, which holds the block's size.
+pub const BLOCK_HEADER_SIZE: usize = 4;
+
+/// Size of the end mark, an empty block header.
+pub const END_MARK_SIZE: usize = 4;
+
+/// Size of a checksum.
+pub const CHECKSUM_SIZE: usize = 4;
+
+/// Set in a block header if the block is stored uncompressed.
+pub const UNCOMPRESSED_FLAG: u32 = 0x80000000;
+
+/// Version number stored in bits 7 and 6 of FLG.
+const VERSION: u8 = 1;
+
+/// FLG bits
+const FLG_VERSION_MASK: u8 = 0xC0;
+const FLG_BLOCK_INDEPENDENCE: u8 = 0x20;
+const FLG_BLOCK_CHECKSUM: u8 = 0x10;
+const FLG_CONTENT_SIZE: u8 = 0x08;
+const FLG_CONTENT_CHECKSUM: u8 = 0x04;
+const FLG_RESERVED: u8 = 0x02;
+const FLG_DICT_ID: u8 = 0x01;
+
+/// BD bits
+const BD_BLOCK_SIZE_MASK: u8 = 0x70;
+const BD_RESERVED: u8 = 0x8F;
+
+/// Everything a frame header says about its frame.
+pub struct FrameHeader {
+    /// What lz4frame would report for the frame in `get_frame_info()`.
+    pub info: FrameInfo,
+    /// Whether every block is followed by its checksum.
+    pub block_checksum: bool,
+    /// ID of the dictionary the frame was compressed with, if any.
+    pub dict_id: Option<u32>,
+    /// For skippable frames, the number of bytes to skip after the header.
+    pub skip_size: usize,
+}
+
+/// Returns the size of the header `write_header` writes for a frame described by `info`.
+pub fn header_size(info: &FrameInfo) -> usize {
+    let mut size: usize = MIN_HEADER_SIZE;
+    if info.content_size != 0 { size += 8; }
+    if info.dict_id != 0 { size += DICT_ID_SIZE; }
+    size
+}
+
+/// Writes the header of a frame described by `info` into `dst`. `BlockSize::Default` is written
+/// as 64 KB blocks, and a `content_size` or `dict_id` of 0 means that field is left out.
+/// The result is either the number of bytes written or an `Lz4Error` if `dst` is too small.
+pub fn write_header(info: &FrameInfo, dst: &mut [u8]) -> Lz4Result<usize> {
+    let size: usize = header_size(info);
+    if dst.len() < size {
+        return Err(Lz4Error { desc: "destination too small to hold frame header".to_string() });
+    }
+
+    write_u32(dst, 0, MAGIC_NUMBER);
+
+    let mut flg: u8 = VERSION << 6;
+    if info.block_mode == BlockMode::Independent { flg |= FLG_BLOCK_INDEPENDENCE; }
+    if info.content_checksum_flag == ContentChecksum::Enabled { flg |= FLG_CONTENT_CHECKSUM; }
+    if info.block_checksum_flag == BlockChecksum::Enabled { flg |= FLG_BLOCK_CHECKSUM; }
+    if info.content_size != 0 { flg |= FLG_CONTENT_SIZE; }
+    if info.dict_id != 0 { flg |= FLG_DICT_ID; }
+    dst[4] = flg;
+
+    let block_size_id: u8 = match info.block_size_id {
+        BlockSize::Default => BlockSize::Max64KB as u8,
+        id => id as u8,
+    };
+    dst[5] = block_size_id << 4;
+
+    let mut pos: usize = 6;
+    if info.content_size != 0 {
+        write_u32(dst, pos, info.content_size as u32);
+        write_u32(dst, pos + 4, (info.content_size >> 32) as u32);
+        pos += 8;
+    }
+    if info.dict_id != 0 {
+        write_u32(dst, pos, info.dict_id);
+        pos += DICT_ID_SIZE;
+    }
+
+    dst[pos] = header_checksum(dst.slice_to(pos), false);
+    Ok(pos + 1)
+}
+
+/// Computes the header checksum of a frame whose header up to the checksum is `header`: the
+/// second byte of the hash of the frame descriptor. With `legacy`, the magic number is hashed as
+/// well, like Kafka did before message format v1.
+pub fn header_checksum(header: &[u8], legacy: bool) -> u8 {
+    let start: usize = if legacy { 0 } else { 4 };
+    (xxh32(header.slice_from(start), 0) >> 8) as u8
+}
+
+/// Replaces the checksum at the end of the frame header `header` with the one `header_checksum`
+/// computes for it, switching a header between the standard and the legacy checksum.
+pub fn rewrite_header_checksum(header: &mut [u8], legacy: bool) {
+    let pos: usize = header.len() - 1;
+    header[pos] = header_checksum(header.slice_to(pos), legacy);
+}
+
+/// Writes `value` at `pos` as 4 little endian bytes.
+pub fn write_u32(dst: &mut [u8], pos: usize, value: u32) {
+    dst[pos] = value as u8;
+    dst[pos + 1] = (value >> 8) as u8;
+    dst[pos + 2] = (value >> 16) as u8;
+    dst[pos + 3] = (value >> 24) as u8;
+}
+
+/// Returns how many bytes the header at the start of `src` takes up, which can only be known once
+/// its magic number and FLG byte are there. Until then, this is `MIN_HEADER_SIZE`.
+pub fn required_header_size(src: &[u8]) -> usize {
+    if src.len() >= 4 && is_skippable(read_u32(src, 0)) {
+        return SKIPPABLE_HEADER_SIZE;
+    }
+    if src.len() < 5 {
+        return MIN_HEADER_SIZE;
+    }
+    let flg: u8 = src[4];
+    let mut size: usize = MIN_HEADER_SIZE;
+    if flg & FLG_CONTENT_SIZE != 0 { size += 8; }
+    if flg & FLG_DICT_ID != 0 { size += DICT_ID_SIZE; }
+    size
+}
+
+/// Parses the frame header at the start of `src`. The result is `None` if `src` doesn't hold the
+/// whole header yet, and otherwise the parsed header together with its size. An `Lz4Error` is
+/// returned if the header is invalid or its checksum doesn't match. With `legacy`, the checksum
+/// is expected to cover the magic number too, see `header_checksum`.
+pub fn read_header(src: &[u8], legacy: bool) -> Lz4Result<Option<(FrameHeader, usize)>> {
+    let size: usize = required_header_size(src);
+    if src.len() < size {
+        return Ok(None);
+    }
+
+    let magic: u32 = read_u32(src, 0);
+    if is_skippable(magic) {
+        let mut info: FrameInfo = Default::default();
+        info.frame_type = FrameType::SkippableFrame;
+        let header: FrameHeader = FrameHeader {
+            info: info,
+            block_checksum: false,
+            dict_id: None,
+            skip_size: read_u32(src, 4) as usize,
+        };
+        return Ok(Some((header, size)));
+    }
+    if magic != MAGIC_NUMBER {
+        return Err(invalid_header("unknown magic number"));
+    }
+
+    let flg: u8 = src[4];
+    let bd: u8 = src[5];
+    if (flg & FLG_VERSION_MASK) >> 6 != VERSION {
+        return Err(invalid_header("unsupported frame version"));
+    }
+    if flg & FLG_RESERVED != 0 || bd & BD_RESERVED != 0 {
+        return Err(invalid_header("reserved bits set"));
+    }
+    let block_size_id: BlockSize = match (bd & BD_BLOCK_SIZE_MASK) >> 4 {
+        4 => BlockSize::Max64KB,
+        5 => BlockSize::Max256KB,
+        6 => BlockSize::Max1MB,
+        7 => BlockSize::Max4MB,
+        _ => { return Err(invalid_header("invalid maximum block size")); },
+    };
+
+    let mut pos: usize = 6;
+    let mut content_size: u64 = 0;
+    if flg & FLG_CONTENT_SIZE != 0 {
+        content_size = (read_u32(src, pos) as u64) | ((read_u32(src, pos + 4) as u64) << 32);
+        pos += 8;
+    }
+    let mut dict_id: Option<u32> = None;
+    if flg & FLG_DICT_ID != 0 {
+        dict_id = Some(read_u32(src, pos));
+        pos += DICT_ID_SIZE;
+    }
+    if src[pos] != header_checksum(src.slice_to(pos), legacy) {
+        return Err(invalid_header("header checksum mismatch"));
+    }
+
+    let info: FrameInfo = FrameInfo {
+        block_size_id: block_size_id,
+        block_mode: if flg & FLG_BLOCK_INDEPENDENCE != 0 { BlockMode::Independent }
+                    else { BlockMode::Linked },
+        content_checksum_flag: if flg & FLG_CONTENT_CHECKSUM != 0 { ContentChecksum::Enabled }
+                               else { ContentChecksum::Disabled },
+        frame_type: FrameType::Default,
+        content_size: content_size,
+        dict_id: dict_id.unwrap_or(0),
+        block_checksum_flag: if flg & FLG_BLOCK_CHECKSUM != 0 { BlockChecksum::Enabled }
+                             else { BlockChecksum::Disabled },
+    };
+    let header: FrameHeader = FrameHeader {
+        info: info,
+        block_checksum: flg & FLG_BLOCK_CHECKSUM != 0,
+        dict_id: dict_id,
+        skip_size: 0,
+    };
+    Ok(Some((header, size)))
+}
+
+/// Whether `magic` is the magic number of a skippable frame.
+fn is_skippable(magic: u32) -> bool {
+    magic & 0xFFFFFFF0 == SKIPPABLE_MAGIC_NUMBER
+}
+
+/// Creates the error for a block whose checksum doesn't match, naming the block by its index in
+/// the frame, starting at 0.
+pub fn block_checksum_mismatch(index: u64) -> Lz4Error {
+    Lz4Error { desc: format!("block checksum mismatch in block {}", index) }
+}
+
+/// Creates the error for a frame whose content checksum doesn't match its content.
+pub fn content_checksum_mismatch() -> Lz4Error {
+    Lz4Error { desc: "content checksum mismatch".to_string() }
+}
+
+/// Creates the error for a frame naming the dictionary it was compressed with. Its blocks can't be
+/// decoded without that dictionary, and there's no way to supply one yet.
+pub fn dictionary_not_given(dict_id: u32) -> Lz4Error {
+    Lz4Error { desc: format!("frame was compressed with dictionary ID {}, and no dictionary was \
+                              given", dict_id) }
+}
+
+fn invalid_header(reason: &str) -> Lz4Error {
+    Lz4Error { desc: format!("invalid frame header: {}", reason) }
+}
+
+/// Reads 4 bytes at `pos` as a little endian integer.
+pub fn read_u32(src: &[u8], pos: usize) -> u32 {
+    (src[pos] as u32) | ((src[pos + 1] as u32) << 8) |
+        ((src[pos + 2] as u32) << 16) | ((src[pos + 3] as u32) << 24)
+}
+
+/// Tests that frame headers survive being written and read back.
+mod header_tests {
+    use std::default::Default;
+    use lz4rs_sys::{
+        FrameInfo,
+        BlockSize,
+        BlockMode,
+        ContentChecksum,
+        BlockChecksum,
+    };
+    use super::{write_header, read_header, rewrite_header_checksum, MAX_HEADER_SIZE};
+
+    #[test]
+    fn round_trip() {
+        let mut info: FrameInfo = Default::default();
+        info.block_size_id = BlockSize::Max1MB;
+        info.block_mode = BlockMode::Independent;
+        info.content_checksum_flag = ContentChecksum::Enabled;
+        info.block_checksum_flag = BlockChecksum::Enabled;
+        info.content_size = 0x123456789;
+        info.dict_id = 0xCAFE;
+
+        let mut buf: [u8; MAX_HEADER_SIZE] = [0; MAX_HEADER_SIZE];
+        let len: usize = write_header(&info, &mut buf).ok().unwrap();
+        assert_eq!(len, MAX_HEADER_SIZE);
+        assert!(read_header(buf.slice_to(len - 1), false).ok().unwrap().is_none());
+
+        let (header, size) = read_header(&buf, false).ok().unwrap().unwrap();
+        assert_eq!(size, len);
+        assert_eq!(header.info.block_size_id, BlockSize::Max1MB);
+        assert_eq!(header.info.block_mode, BlockMode::Independent);
+        assert_eq!(header.info.content_checksum_flag, ContentChecksum::Enabled);
+        assert_eq!(header.info.block_checksum_flag, BlockChecksum::Enabled);
+        assert!(header.block_checksum);
+        assert_eq!(header.info.content_size, 0x123456789);
+        assert_eq!(header.info.dictionary_id(), Some(0xCAFE));
+        assert_eq!(header.dict_id, Some(0xCAFE));
+
+        buf[len - 1] ^= 1;
+        assert!(read_header(&buf, false).is_err());
+    }
+
+    /// Tests Kafka's legacy header checksum, which covers the magic number, on a header for
+    /// independent 64 KB blocks.
+    #[test]
+    fn legacy_checksum() {
+        let mut buf: [u8; 7] = [0x04, 0x22, 0x4d, 0x18, 0x60, 0x40, 0x1a];
+        assert!(read_header(&buf, false).is_err());
+        let (header, size) = read_header(&buf, true).ok().unwrap().unwrap();
+        assert_eq!(size, 7);
+        assert_eq!(header.info.block_mode, BlockMode::Independent);
+
+        rewrite_header_checksum(&mut buf, false);
+        assert_eq!(buf[6], 0x82);
+        assert!(read_header(&buf, false).ok().unwrap().is_some());
+        assert!(read_header(&buf, true).is_err());
+    }
+}
@@ -1,23 +1,82 @@
 //! This module contains all the associated types, structs, methods, and functions for use with the
 //! LZ4 Framing Format.
+//!
+//! A `CompressionContext` or `DecompressionContext` can be handed to another thread, since an
+//! lz4frame context isn't tied to the thread that created it, so both are `Send`. Neither is
+//! `Sync`: every call changes the context's state.
 
 extern crate libc;
 extern crate collections;
 
-use super::liblz4::frame::LZ4F_isError;
-use super::liblz4::frame::types::FrameErrorCode;
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::LZ4F_isError;
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::FrameErrorCode;
+
+#[cfg(all(feature = "mmap", unix))]
+use std::io::{IoResult, IoError, IoErrorKind};
+#[cfg(all(feature = "mmap", unix))]
+use std::io::fs::File;
+#[cfg(all(feature = "mmap", unix))]
+use std::os::{MemoryMap, MapOption};
+#[cfg(all(feature = "mmap", unix))]
+use std::os::unix::AsRawFd;
 
 
 pub use self::structs::{
     Lz4Error,
     Lz4Result,
-    FrameContextType,
-    Lz4FrameContext,
+    CompressOptions,
+    DecompressOptions,
+    FrameStatus,
+};
+
+pub use self::compress::CompressionContext;
+#[cfg(feature = "std")]
+pub use self::compress::CompressingReader;
+pub use self::decompress::DecompressionContext;
+#[cfg(feature = "std")]
+pub use self::decompress::DecompressingWriter;
+#[cfg(feature = "std")]
+pub use self::pool::ContextPool;
+
+pub use lz4rs_sys::{
+    BlockSize,
+    BlockMode,
+    ContentChecksum,
+    BlockChecksum,
+    FrameType,
+    FrameInfo,
+    FramePreferences,
+    FrameCompressOptions,
+    FrameDecompressOptions,
 };
 
 pub mod structs;
 pub mod compress;
 pub mod decompress;
+#[cfg(feature = "std")]
+pub mod pool;
+
+mod header;
+mod encoder;
+mod decoder;
+
+/// The Rust implementation of the frame format, which the contexts, `Compressor` and
+/// `Decompressor` use when the `pure-rust` feature is enabled. It is built either way, so that
+/// frames written by the C library can be checked against it, and the other way around.
+pub mod rust {
+    pub use super::encoder::{
+        FrameEncoder,
+        compress_bound,
+        compress_frame_bound,
+        compress_frame,
+    };
+    pub use super::decoder::FrameDecoder;
+}
 
 
 /**************************************
@@ -25,6 +84,7 @@ pub mod decompress;
  * ************************************/
 
 /// checks if the input is an LZ4 error code
+#[cfg(not(feature = "pure-rust"))]
 pub fn is_error(code: FrameErrorCode) -> bool {
     let result: usize = unsafe { LZ4F_isError(code) as usize };
     result != 0
@@ -33,6 +93,7 @@ pub fn is_error(code: FrameErrorCode) -> bool {
 /// Checks whether the given code is an error code. If not (i.e. when it's some number of bytes
 /// compressed/decompressed), returns the code as a usize. Else, returns an Lz4Error with the
 /// message associated with the code.
+#[cfg(not(feature = "pure-rust"))]
 pub fn maybe_error(code: FrameErrorCode) -> Lz4Result<usize> {
     if is_error(code) {
         Err(Lz4Error::new(code))
@@ -41,12 +102,54 @@ pub fn maybe_error(code: FrameErrorCode) -> Lz4Result<usize> {
     }
 }
 
+/// Maps the first `len` bytes of `file` into memory. Mappings are read-only and private unless
+/// `writable` is set, in which case they are shared so that writes go through to the file. `file`
+/// has to be opened for reading and writing in that case.
+#[cfg(all(feature = "mmap", unix))]
+fn map_file(file: &File, len: usize, writable: bool) -> IoResult<MemoryMap> {
+    let fd = file.as_raw_fd();
+    let options: Vec<MapOption> = if writable {
+        vec![MapOption::MapReadable,
+             MapOption::MapWritable,
+             MapOption::MapFd(fd),
+             MapOption::MapNonStandardFlags(libc::MAP_SHARED)]
+    } else {
+        vec![MapOption::MapReadable, MapOption::MapFd(fd)]
+    };
+    match MemoryMap::new(len, options.as_slice()) {
+        Ok(map) => Ok(map),
+        Err(e) => {
+            Err(IoError {
+                kind: IoErrorKind::OtherIoError,
+                desc: "mmap error",
+                detail: Some(format!("{:?}", e)),
+            })
+        },
+    }
+}
 
-/// Simple tests that the Compressor/Decompressor work as expected.
+
+/// Simple tests that the Compressor/Decompressor work as expected. See the crate docs for how
+/// they run under Miri.
+#[cfg(all(feature = "std", any(feature = "pure-rust", not(miri))))]
 mod basic_functionality_tests {
-    use std::io::MemReader;
-    use super::compress::Compressor;
-    use super::decompress::Decompressor;
+    use std::io::{MemReader, IoErrorKind};
+    use super::compress::{Compressor, CompressingReader};
+    use super::decompress::{Decompressor, DecompressingWriter, decompress_into};
+    use super::compress::{compress_frame, compress_frame_bound, compress_bound};
+    use super::{CompressionContext, DecompressionContext, ContextPool};
+    use super::{Lz4Result, FramePreferences, CompressOptions, DecompressOptions, FrameStatus};
+    use super::ContentChecksum;
+    use std::default::Default;
+    use std::iter::repeat;
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    use std::io::{File, TempDir};
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    use std::io::fs::PathExtensions;
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    use super::compress::compress_file_mmap;
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    use super::decompress::decompress_file_mmap;
 
     /// Tests to ensure that we can compress some data and then receive the same data back when
     /// decompressing
@@ -65,4 +168,681 @@ mod basic_functionality_tests {
         let bytes_decompressed: usize = decompressor.read(&mut buf).unwrap();
         assert_eq!(data, buf.slice_to(bytes_decompressed));
     }
+
+    /// Tests that compressing several slices with `compress_slices` yields the same data back as
+    /// their concatenation.
+    #[test]
+    fn compress_slices_works() {
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        let slices: [&[u8]; 3] = [b"This is a test\n", b"A what?\nA test\n", b"Oh a test\n"];
+        compressor.compress_slices(&slices).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let mut buf: [u8; 1024] = [0; 1024];
+        let bytes_decompressed: usize = decompressor.read(&mut buf).unwrap();
+        assert_eq!(b"This is a test\nA what?\nA test\nOh a test\n",
+                   buf.slice_to(bytes_decompressed));
+    }
+
+    /// Tests `compress_slices` with linked blocks and slices larger than a block, which are
+    /// dropped before the frame is continued: the data written afterwards repeats theirs, so it
+    /// is compressed against the dictionary they left behind.
+    #[test]
+    fn compress_slices_linked_blocks() {
+        let mut data: Vec<u8> = Vec::new();
+        let mut state: u64 = 1;
+        while data.len() < 300 * 1024 {
+            state = (state * 1103515245 + 12345) & 0x7FFFFFFF;
+            data.push(b"abcdefgh"[(state >> 16) as usize & 7]);
+        }
+
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        {
+            let first: Vec<u8> = data.slice_to(200 * 1024).to_vec();
+            let second: Vec<u8> = data.slice(200 * 1024, 270 * 1024).to_vec();
+            let third: Vec<u8> = data.slice(270 * 1024, 270 * 1024 + 10).to_vec();
+            let slices: [&[u8]; 3] = [first.as_slice(), second.as_slice(), third.as_slice()];
+            compressor.compress_slices(&slices).unwrap();
+        }
+        // reuse the memory the slices were in
+        let garbage: Vec<u8> = repeat(b'z').take(270 * 1024).collect();
+        compressor.write(data.slice_from(270 * 1024 + 10)).unwrap();
+        compressor.write(data.slice(260 * 1024, 270 * 1024)).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+        assert_eq!(garbage.len(), 270 * 1024);
+
+        let mut expected: Vec<u8> = data.clone();
+        expected.push_all(data.slice(260 * 1024, 270 * 1024));
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(expected, decompressor.read_to_end().unwrap());
+    }
+
+    /// Tests that `Compressor` and `Decompressor` ignore the stable buffer flags, which don't hold
+    /// for the buffers they're given: every buffer is overwritten right after each call, and a
+    /// linked frame of several blocks still round-trips.
+    #[test]
+    fn stable_flags_are_ignored() {
+        let mut data: Vec<u8> = Vec::new();
+        let mut state: u64 = 7;
+        while data.len() < 300 * 1024 {
+            state = (state * 1103515245 + 12345) & 0x7FFFFFFF;
+            data.push(b"abcdefgh"[(state >> 16) as usize & 7]);
+        }
+
+        let opts: CompressOptions = unsafe { CompressOptions::new(true) };
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), None, None,
+                                                                  Some(opts)).ok().unwrap();
+        let mut chunk: Vec<u8> = repeat(0u8).take(10000).collect();
+        for piece in data.chunks(10000) {
+            chunk.truncate(0);
+            chunk.push_all(piece);
+            compressor.write(chunk.as_slice()).unwrap();
+            for b in chunk.iter_mut() { *b = b'z'; }
+        }
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let opts: DecompressOptions = unsafe { DecompressOptions::new(true) };
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> =
+            Decompressor::with_options(readr, None, Some(opts)).ok().unwrap();
+        let mut decompressed: Vec<u8> = Vec::new();
+        let mut buf: [u8; 1000] = [0; 1000];
+        loop {
+            match decompressor.read(&mut buf) {
+                Ok(n) => {
+                    decompressed.push_all(buf.slice_to(n));
+                    for b in buf.iter_mut() { *b = b'z'; }
+                },
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+                Err(e) => { panic!("{:?}", e); },
+            }
+        }
+        assert_eq!(data, decompressed);
+    }
+
+    /// Tests that `read_prefix` returns the start of the content and leaves the frame to be read
+    /// from the beginning.
+    #[test]
+    fn read_prefix_works() {
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        compressor.write(data).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let prefix: Vec<u8> = decompressor.read_prefix(14).unwrap();
+        assert_eq!(b"This is a test", prefix.as_slice());
+
+        let mut buf: [u8; 1024] = [0; 1024];
+        let bytes_decompressed: usize = decompressor.read(&mut buf).unwrap();
+        assert_eq!(data, buf.slice_to(bytes_decompressed));
+    }
+
+    /// Tests that `decompress_into` decompresses a whole frame into a fixed buffer, and refuses
+    /// to start when the declared content size doesn't fit.
+    #[test]
+    fn decompress_into_works() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.content_size = data.len() as u64;
+        let bound: usize = compress_frame_bound(data.len(), Some(&prefs));
+        let mut frame: Vec<u8> = repeat(0u8).take(bound).collect();
+        let len: usize = compress_frame(frame.as_mut_slice(), data, Some(&prefs)).ok().unwrap();
+
+        let mut buf: [u8; 64] = [0; 64];
+        let n: usize = decompress_into(frame.slice_to(len), &mut buf, None).ok().unwrap();
+        assert_eq!(data, buf.slice_to(n));
+
+        let mut small: [u8; 16] = [0; 16];
+        assert!(decompress_into(frame.slice_to(len), &mut small, None).is_err());
+    }
+
+    /// Tests that frames written with Kafka's legacy header checksum are only read back when the
+    /// legacy checksum is expected.
+    #[test]
+    fn legacy_header_checksum() {
+        let opts: CompressOptions = Default::default();
+        let opts: CompressOptions = opts.with_legacy_header_checksum(true);
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), None, None,
+                                                                  Some(opts)).ok().unwrap();
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        compressor.write(data).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let mut buf: [u8; 1024] = [0; 1024];
+        let readr: MemReader = MemReader::new(v.clone());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert!(decompressor.read(&mut buf).is_err());
+
+        let opts: DecompressOptions = Default::default();
+        let opts: DecompressOptions = opts.with_legacy_header_checksum(true);
+        assert!(decompress_into(v.as_slice(), &mut buf, None).is_err());
+        let n: usize = decompress_into(v.as_slice(), &mut buf, Some(&opts)).ok().unwrap();
+        assert_eq!(data, buf.slice_to(n));
+
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> =
+            Decompressor::with_options(readr, None, Some(opts)).ok().unwrap();
+        let bytes_decompressed: usize = decompressor.read(&mut buf).unwrap();
+        assert_eq!(data, buf.slice_to(bytes_decompressed));
+    }
+
+    /// Tests that block checksums are verified, and that a mismatch names the block that failed.
+    #[test]
+    fn block_checksums() {
+        let mut frame: Vec<u8> = Vec::new();
+        frame.push_all(b"\x04\x22\x4d\x18\x70\x40\xad");
+        frame.push_all(b"\x04\x00\x00\x80abcd\x05\x37\x64\xa3");
+        frame.push_all(b"\x04\x00\x00\x80efgh\xdf\x09\x02\x33");
+        frame.push_all(b"\x00\x00\x00\x00");
+        let mut buf: [u8; 16] = [0; 16];
+        let n: usize = decompress_into(frame.as_slice(), &mut buf, None).ok().unwrap();
+        assert_eq!(b"abcdefgh", buf.slice_to(n));
+
+        frame[30] ^= 1;
+        let err = decompress_into(frame.as_slice(), &mut buf, None).err().unwrap();
+        assert_eq!(err.desc.as_slice(), "block checksum mismatch in block 1");
+    }
+
+    /// Tests that the `Compressor` writes block checksums and that the `Decompressor` reports
+    /// which block doesn't match them.
+    #[test]
+    fn block_checksums_round_trip() {
+        use super::BlockChecksum;
+
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.block_checksum_flag = BlockChecksum::Enabled;
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), Some(prefs), None,
+                                                                  None).ok().unwrap();
+        let data: Vec<u8> = (0..3 * 64 * 1024).map(|i| (i % 251) as u8).collect();
+        compressor.write(data.as_slice()).unwrap();
+        let (mut v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let readr: MemReader = MemReader::new(v.clone());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap());
+
+        // corrupt the checksum after the second block
+        let first: usize = 7 + 4 + (read_size(v.as_slice(), 7) + 4);
+        let second: usize = first + 4 + read_size(v.as_slice(), first);
+        v[second] ^= 1;
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let err = decompressor.read_to_end().err().unwrap();
+        assert!(err.detail.unwrap().as_slice().contains("in block 1"));
+    }
+
+    /// Tests that frames with block checksums stream through a `DecompressionContext` handed a bit
+    /// at a time and through the `Decompressor` with either backend, and that the `Decompressor`
+    /// reports which block doesn't match its checksum.
+    #[test]
+    fn block_checksums_streaming() {
+        use super::BlockChecksum;
+        use super::rust;
+        use std::cmp::min;
+
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.block_checksum_flag = BlockChecksum::Enabled;
+        prefs.frame_info.content_checksum_flag = ContentChecksum::Enabled;
+        let data: Vec<u8> = (0..3 * 64 * 1024).map(|i| (i % 251) as u8).collect();
+        let bound: usize = rust::compress_frame_bound(data.len(), &prefs);
+        let mut frame: Vec<u8> = repeat(0u8).take(bound).collect();
+        let len: usize = rust::compress_frame(frame.as_mut_slice(), data.as_slice(), &prefs)
+            .ok().unwrap();
+        frame.truncate(len);
+
+        let mut dctx: DecompressionContext = DecompressionContext::new().ok().unwrap();
+        let (finfo, mut ip, _) = dctx.frame_info(frame.as_slice()).ok().unwrap();
+        assert_eq!(finfo.block_checksum_flag, BlockChecksum::Enabled);
+        let mut decompressed: Vec<u8> = Vec::new();
+        let mut buf: [u8; 1000] = [0; 1000];
+        loop {
+            let end: usize = min(ip + 777, frame.len());
+            let (consumed, written, hint) = dctx.decompress(&mut buf, frame.slice(ip, end), None)
+                                                .ok().unwrap();
+            ip += consumed;
+            decompressed.push_all(buf.slice_to(written));
+            if hint == 0 { break; }
+        }
+        assert_eq!(ip, frame.len());
+        assert_eq!(data, decompressed);
+
+        let readr: MemReader = MemReader::new(frame.clone());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap());
+
+        // corrupt the data of the second block
+        let first: usize = 7 + 4 + (read_size(frame.as_slice(), 7) + 4);
+        frame[first + 4] ^= 1;
+        let readr: MemReader = MemReader::new(frame);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let err = decompressor.read_to_end().err().unwrap();
+        assert_eq!(err.detail.unwrap().as_slice(), "block checksum mismatch in block 1");
+    }
+
+    /// Tests that a frame written with an expected size declares it, and that the `Compressor`
+    /// refuses to write more or less than that.
+    #[test]
+    fn content_size_works() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut compressor: Compressor<Vec<u8>> =
+            Compressor::with_content_size(Vec::new(), data.len() as u64, None, None).ok().unwrap();
+        compressor.write(data).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(decompressor.content_size().unwrap(), Some(data.len() as u64));
+        let mut buf: [u8; 1024] = [0; 1024];
+        let bytes_decompressed: usize = decompressor.read(&mut buf).unwrap();
+        assert_eq!(data, buf.slice_to(bytes_decompressed));
+
+        let mut compressor: Compressor<Vec<u8>> =
+            Compressor::with_content_size(Vec::new(), 4, None, None).ok().unwrap();
+        assert!(compressor.write(data).is_err());
+        let mut compressor: Compressor<Vec<u8>> =
+            Compressor::with_content_size(Vec::new(), 1024, None, None).ok().unwrap();
+        compressor.write(data).unwrap();
+        assert!(compressor.done().1.is_err());
+    }
+
+    /// Tests that contexts can be handed to another thread, and that a frame written through a
+    /// `CompressionContext` reads back through a `DecompressionContext`.
+    #[test]
+    fn contexts_work() {
+        fn assert_send<T: Send>(_: &T) {}
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+
+        let mut cctx: CompressionContext = CompressionContext::new().ok().unwrap();
+        assert_send(&cctx);
+        let size: usize = 15 + compress_bound(data.len(), None);
+        let mut frame: Vec<u8> = repeat(0u8).take(size).collect();
+        let mut len: usize = cctx.begin(frame.as_mut_slice(), None).ok().unwrap();
+        len += cctx.update(frame.slice_from_mut(len), data, None).ok().unwrap();
+        len += cctx.end(frame.slice_from_mut(len), None).ok().unwrap();
+
+        let mut dctx: DecompressionContext = DecompressionContext::new().ok().unwrap();
+        assert_send(&dctx);
+        let mut buf: [u8; 1024] = [0; 1024];
+        let (consumed, written, hint) = dctx.decompress(&mut buf, frame.slice_to(len), None)
+                                            .ok().unwrap();
+        assert_eq!(consumed, len);
+        assert_eq!(hint, 0);
+        assert_eq!(data, buf.slice_to(written));
+    }
+
+    /// Tests that the frame functions stay within the slices they are given: compressing into a
+    /// buffer that's too small fails, and decompressing into a small one takes several calls.
+    #[test]
+    fn slice_sizes_are_respected() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let bound: usize = compress_frame_bound(data.len(), None);
+        let mut frame: Vec<u8> = repeat(0u8).take(bound).collect();
+        assert!(compress_frame(frame.slice_to_mut(8), data, None).is_err());
+        let len: usize = compress_frame(frame.as_mut_slice(), data, None).ok().unwrap();
+
+        let mut dctx: DecompressionContext = DecompressionContext::new().ok().unwrap();
+        let mut buf: [u8; 7] = [0; 7];
+        let mut out: Vec<u8> = Vec::new();
+        let mut pos: usize = 0;
+        loop {
+            let (consumed, written, hint) = dctx.decompress(&mut buf, frame.slice(pos, len), None)
+                                                .ok().unwrap();
+            assert!(consumed <= len - pos && written <= buf.len());
+            pos += consumed;
+            out.push_all(buf.slice_to(written));
+            if hint == 0 { break; }
+        }
+        assert_eq!(pos, len);
+        assert_eq!(data, out.as_slice());
+    }
+
+    /// Tests that a frame lz4 1.9 compressed with a dictionary reports its dictionary ID with
+    /// either backend, and fails clearly instead of decoding without the dictionary.
+    #[test]
+    fn dict_id_frames() {
+        // `lz4 -D` with "The quick brown fox jumps over the lazy dog. " as the dictionary, with
+        // dictionary ID 42 added to the header
+        let frame: &[u8] = b"\x04\x22\x4d\x18\x65\x40\x2a\x00\x00\x00\xcd\
+                             \x15\x00\x00\x00\x08\x0e\x00\x0c\x26\x00\x0b\x45\x00\x2f\x2e\
+                             \x20\x5a\x00\x02\x50\x75\x6d\x70\x73\x2e\
+                             \x00\x00\x00\x00\xc5\xbd\x38\x70";
+        let expected: &str = "frame was compressed with dictionary ID 42, and no dictionary was \
+                              given";
+
+        let mut dctx: DecompressionContext = DecompressionContext::new().ok().unwrap();
+        let (finfo, size, _) = dctx.frame_info(frame).ok().unwrap();
+        assert_eq!(size, 11);
+        assert_eq!(finfo.dictionary_id(), Some(42));
+        let mut buf: [u8; 128] = [0; 128];
+        let err = dctx.decompress(&mut buf, frame.slice_from(size), None).err().unwrap();
+        assert_eq!(err.desc.as_slice(), expected);
+        assert!(dctx.in_frame());
+
+        assert_eq!(decompress_into(frame, &mut buf, None).err().unwrap().desc.as_slice(), expected);
+
+        let readr: MemReader = MemReader::new(frame.to_vec());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let err = decompressor.read_to_end().err().unwrap();
+        assert_eq!(err.detail.unwrap().as_slice(), expected);
+
+        let readr: MemReader = MemReader::new(frame.to_vec());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        let err = decompressor.read_prefix(4).err().unwrap();
+        assert_eq!(err.detail.unwrap().as_slice(), expected);
+
+        // without a dictionary to compress with, neither backend writes such a frame
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info = prefs.frame_info.with_dict_id(Some(42));
+        let result: Lz4Result<Compressor<Vec<u8>>> = Compressor::new(Vec::new(), Some(prefs),
+                                                                     None, None);
+        assert_eq!(result.err().unwrap().desc.as_slice(), expected);
+    }
+
+    /// Tests that frames written by the C library decode with the Rust implementation, and the
+    /// other way around, with linked and independent blocks.
+    #[cfg(not(feature = "pure-rust"))]
+    #[test]
+    fn backends_are_compatible() {
+        use super::{BlockSize, BlockMode};
+        use super::rust;
+
+        let alphabet: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+        let data: Vec<u8> = (0..200 * 1024).map(|i| alphabet[(i / 5 + i / 777) % 26]).collect();
+        for mode in [BlockMode::Linked, BlockMode::Independent].iter() {
+            let mut prefs: FramePreferences = Default::default();
+            prefs.frame_info.block_size_id = BlockSize::Max64KB;
+            prefs.frame_info.block_mode = *mode;
+            prefs.frame_info.content_checksum_flag = ContentChecksum::Enabled;
+
+            let bound: usize = compress_frame_bound(data.len(), Some(&prefs));
+            let mut c_frame: Vec<u8> = repeat(0u8).take(bound).collect();
+            let len: usize = compress_frame(c_frame.as_mut_slice(), data.as_slice(), Some(&prefs))
+                .ok().unwrap();
+            c_frame.truncate(len);
+            let mut decoder: rust::FrameDecoder = rust::FrameDecoder::new();
+            let mut buf: Vec<u8> = repeat(0u8).take(data.len()).collect();
+            let (consumed, written, hint) = decoder.decompress(buf.as_mut_slice(),
+                                                               c_frame.as_slice()).ok().unwrap();
+            assert_eq!((consumed, written, hint), (c_frame.len(), data.len(), 0));
+            assert_eq!(data, buf);
+
+            let bound: usize = rust::compress_frame_bound(data.len(), &prefs);
+            let mut rust_frame: Vec<u8> = repeat(0u8).take(bound).collect();
+            let len: usize = rust::compress_frame(rust_frame.as_mut_slice(), data.as_slice(),
+                                                  &prefs).ok().unwrap();
+            rust_frame.truncate(len);
+            let readr: MemReader = MemReader::new(rust_frame);
+            let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                          .unwrap();
+            assert_eq!(data, decompressor.read_to_end().unwrap());
+        }
+    }
+
+    /// Tests that a `ContextPool` takes back contexts whose frame is done, and drops contexts
+    /// handed back in the middle of a frame, that neither a `Compressor` nor a `Decompressor` takes
+    /// such a context, and that a pooled context forgets the options of its last user.
+    #[test]
+    fn context_pool_reuses_contexts() {
+        let pool: ContextPool = ContextPool::new(4);
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut frame: Vec<u8> = Vec::new();
+        for _ in 0..2 {
+            let mut compressor: Compressor<Vec<u8>> = pool.compressor(Vec::new(), None, None, None)
+                                                          .ok().unwrap();
+            compressor.write(data).unwrap();
+            let (v, result): (Vec<u8>, Lz4Result<usize>) = pool.finish_compressor(compressor);
+            result.ok().unwrap();
+            assert_eq!(pool.idle(), (1, 0));
+            frame = v;
+        }
+
+        let readr: MemReader = MemReader::new(frame.clone());
+        let mut decompressor: Decompressor<MemReader> = pool.decompressor(readr, None, None).ok()
+                                                                                          .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap().as_slice());
+        pool.finish_decompressor(decompressor);
+        assert_eq!(pool.idle(), (1, 1));
+
+        // the frame isn't read to its end, so the context can't be reused
+        let readr: MemReader = MemReader::new(frame.clone());
+        let mut decompressor: Decompressor<MemReader> = pool.decompressor(readr, None, None).ok()
+                                                                                          .unwrap();
+        let mut buf: [u8; 4] = [0; 4];
+        decompressor.read(&mut buf).unwrap();
+        pool.finish_decompressor(decompressor);
+        assert_eq!(pool.idle(), (1, 0));
+
+        let mut cctx: CompressionContext = pool.compression_context().ok().unwrap();
+        let mut header: [u8; 19] = [0; 19];
+        cctx.begin(&mut header, None).ok().unwrap();
+        let result: Lz4Result<Compressor<Vec<u8>>> = Compressor::with_context(Vec::new(), cctx,
+                                                                              None, None, None);
+        assert!(result.is_err());
+        let mut dctx: DecompressionContext = pool.decompression_context().ok().unwrap();
+        dctx.frame_info(frame.as_slice()).ok().unwrap();
+        let readr: MemReader = MemReader::new(frame.clone());
+        let result: Lz4Result<Decompressor<MemReader>> = Decompressor::with_context(readr, dctx,
+                                                                                    None, None);
+        assert!(result.is_err());
+
+        let opts: CompressOptions = Default::default();
+        let opts: CompressOptions = opts.with_legacy_header_checksum(true);
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), None, None,
+                                                                  Some(opts)).ok().unwrap();
+        compressor.write(data).unwrap();
+        let (legacy_frame, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+        let opts: DecompressOptions = Default::default();
+        let opts: DecompressOptions = opts.with_legacy_header_checksum(true);
+        let mut dctx: DecompressionContext = pool.decompression_context().ok().unwrap();
+        let mut out: [u8; 1024] = [0; 1024];
+        let (_, _, hint) = dctx.decompress(&mut out, legacy_frame.as_slice(), Some(&opts))
+                               .ok().unwrap();
+        assert_eq!(hint, 0);
+        pool.put_decompression_context(dctx);
+        let mut dctx: DecompressionContext = pool.decompression_context().ok().unwrap();
+        let (_, written, hint) = dctx.decompress(&mut out, frame.as_slice(), None).ok().unwrap();
+        assert_eq!(hint, 0);
+        assert_eq!(data, out.slice_to(written));
+    }
+
+    /// Tests that the bytes following a frame can be got back from a `Decompressor` once the frame
+    /// has been read, either returned by `into_inner` or by seeking back.
+    #[test]
+    fn trailing_bytes_work() {
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(Vec::new()).ok().unwrap();
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        compressor.write(data).unwrap();
+        let (mut v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+        v.push_all(b"TRAILER");
+
+        let readr: MemReader = MemReader::new(v.clone());
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap().as_slice());
+        let (mut readr, mut trailing): (MemReader, Vec<u8>) = decompressor.into_inner();
+        trailing.push_all(readr.read_to_end().unwrap().as_slice());
+        assert_eq!(b"TRAILER", trailing.as_slice());
+
+        let readr: MemReader = MemReader::new(v);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap().as_slice());
+        let mut readr: MemReader = decompressor.into_inner_rewound().unwrap();
+        assert_eq!(b"TRAILER", readr.read_to_end().unwrap().as_slice());
+    }
+
+    /// Tests that a `CompressingReader` reads as a whole frame of the data it wraps, and checks
+    /// the data against a declared content size.
+    #[test]
+    fn compressing_reader_works() {
+        let mut data: Vec<u8> = Vec::new();
+        for _ in 0..250 {
+            data.push_all(b"This is a test\nA what?\nA test\nOh a test\n");
+        }
+        let readr: MemReader = MemReader::new(data.clone());
+        let mut compressing: CompressingReader<MemReader> =
+            CompressingReader::new(readr, None, Some(1000), None).ok().unwrap();
+        let mut frame: Vec<u8> = Vec::new();
+        let mut buf: [u8; 7] = [0; 7];
+        loop {
+            match compressing.read(&mut buf) {
+                Ok(n) => { frame.push_all(buf.slice_to(n)); },
+                Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+                Err(e) => { panic!("{:?}", e); },
+            }
+        }
+
+        let readr: MemReader = MemReader::new(frame);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(data, decompressor.read_to_end().unwrap());
+
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.content_size = data.len() as u64 + 1;
+        let readr: MemReader = MemReader::new(data);
+        let mut compressing: CompressingReader<MemReader> =
+            CompressingReader::new(readr, Some(prefs), None, None).ok().unwrap();
+        assert!(compressing.read_to_end().is_err());
+    }
+
+    /// Tests that a `DecompressingWriter` decompresses a frame written in slices of any size, and
+    /// tells truncated frames, content checksum mismatches and invalid frames apart.
+    #[test]
+    fn decompressing_writer_works() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.content_checksum_flag = ContentChecksum::Enabled;
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), Some(prefs), None,
+                                                                  None).ok().unwrap();
+        compressor.write(data).unwrap();
+        let (frame, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        for size in [1, 5, frame.len()].iter() {
+            let mut writer: DecompressingWriter<Vec<u8>> =
+                DecompressingWriter::new(Vec::new(), Some(16), None).ok().unwrap();
+            for chunk in frame.chunks(*size) {
+                writer.write(chunk).unwrap();
+            }
+            let (v, status): (Vec<u8>, FrameStatus) = writer.finish();
+            assert_eq!(status, FrameStatus::Complete);
+            assert_eq!(data, v.as_slice());
+        }
+        assert!(DecompressingWriter::new(Vec::new(), Some(0), None).is_err());
+
+        let mut writer: DecompressingWriter<Vec<u8>> =
+            DecompressingWriter::new(Vec::new(), None, None).ok().unwrap();
+        writer.write(frame.slice_to(frame.len() - 1)).unwrap();
+        assert_eq!(writer.finish().1, FrameStatus::Incomplete);
+
+        let mut corrupt: Vec<u8> = frame.clone();
+        let last: usize = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        let mut writer: DecompressingWriter<Vec<u8>> =
+            DecompressingWriter::new(Vec::new(), None, None).ok().unwrap();
+        assert!(writer.write(corrupt.as_slice()).is_err());
+        assert_eq!(writer.finish().1, FrameStatus::ChecksumMismatch);
+
+        // a block larger than the maximum block size
+        let mut corrupt: Vec<u8> = frame.clone();
+        corrupt[7] = 0xff;
+        corrupt[8] = 0xff;
+        corrupt[9] = 0xff;
+        let mut writer: DecompressingWriter<Vec<u8>> =
+            DecompressingWriter::new(Vec::new(), None, None).ok().unwrap();
+        assert!(writer.write(corrupt.as_slice()).is_err());
+        match writer.finish().1 {
+            FrameStatus::Invalid(_) => {},
+            status => panic!("{:?}", status),
+        }
+    }
+
+    /// Tests that `compress_file_mmap` compresses a file of several linked blocks straight from
+    /// the mapping, declaring its size.
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    #[test]
+    fn compress_file_mmap_works() {
+        let dir: TempDir = TempDir::new("lz4rs").unwrap();
+        let src: Path = dir.path().join("data");
+        let dst: Path = dir.path().join("data.lz4");
+
+        let data: Vec<u8> = (0..300 * 1024u32).map(|i| (i % 251 + i / 4096) as u8).collect();
+        File::create(&src).write(data.as_slice()).unwrap();
+        let len: usize = compress_file_mmap(&src, &dst).unwrap();
+
+        let frame: Vec<u8> = File::open(&dst).read_to_end().unwrap();
+        assert_eq!(frame.len(), len);
+        let readr: MemReader = MemReader::new(frame);
+        let mut decompressor: Decompressor<MemReader> = Decompressor::new(readr, None).ok()
+                                                                                      .unwrap();
+        assert_eq!(decompressor.content_size().unwrap(), Some(data.len() as u64));
+        assert_eq!(data, decompressor.read_to_end().unwrap());
+    }
+
+    /// Tests that `decompress_file_mmap` decompresses every frame in a file, and refuses a frame
+    /// declaring more content than the file could hold without leaving the output file behind.
+    #[cfg(all(feature = "mmap", unix, not(miri)))]
+    #[test]
+    fn decompress_file_mmap_works() {
+        let dir: TempDir = TempDir::new("lz4rs").unwrap();
+        let src: Path = dir.path().join("frames.lz4");
+        let dst: Path = dir.path().join("frames");
+
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut prefs: FramePreferences = Default::default();
+        prefs.frame_info.content_size = data.len() as u64;
+        let mut compressor: Compressor<Vec<u8>> = Compressor::new(Vec::new(), Some(prefs), None,
+                                                                  None).ok().unwrap();
+        compressor.write(data).unwrap();
+        let (v, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+        let mut compressor: Compressor<Vec<u8>> = Compressor::default(v).ok().unwrap();
+        compressor.write(data).unwrap();
+        let (frames, result): (Vec<u8>, Lz4Result<usize>) = compressor.done();
+        result.ok().unwrap();
+
+        File::create(&src).write(frames.as_slice()).unwrap();
+        assert_eq!(decompress_file_mmap(&src, &dst).unwrap(), 2 * data.len());
+        let mut expected: Vec<u8> = data.to_vec();
+        expected.push_all(data);
+        assert_eq!(expected, File::open(&dst).read_to_end().unwrap());
+
+        // a frame declaring 2^60 bytes of content
+        File::create(&src).write(b"\x04\x22\x4d\x18\x68\x40\x00\x00\x00\x00\x00\x00\x00\x10\x49\
+                                   \x00\x00\x00\x00").unwrap();
+        assert!(decompress_file_mmap(&src, &dst).is_err());
+        assert!(!dst.exists());
+    }
+
+    /// Reads the size of the block whose header is at `pos`.
+    fn read_size(frame: &[u8], pos: usize) -> usize {
+        let value: usize = (frame[pos] as usize) | ((frame[pos + 1] as usize) << 8) |
+            ((frame[pos + 2] as usize) << 16) | ((frame[pos + 3] as usize) << 24);
+        value & 0x7FFFFFFF
+    }
 }
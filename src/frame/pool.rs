@@ -0,0 +1,128 @@
+//! This module contains the `ContextPool` struct, which keeps compression and decompression
+//! contexts around between frames so that short-lived `Compressor`s and `Decompressor`s don't each
+//! have to create (and free) a context of their own.
+
+use std::sync::Mutex;
+
+use lz4rs_sys::FramePreferences;
+
+use super::structs::{
+    Lz4Result,
+    CompressOptions,
+    DecompressOptions,
+};
+use super::compress::{
+    CompressionContext,
+    Compressor,
+    create_compression_context,
+};
+use super::decompress::{
+    DecompressionContext,
+    Decompressor,
+    create_decompression_context,
+};
+
+/// A thread-safe pool of idle compression and decompression contexts. Contexts are taken out of
+/// the pool, or created if none are idle, and put back once their frame is done. A context put
+/// back in the middle of a frame is dropped instead, since it can't start another one.
+/// To share a pool between threads, wrap it in an `Arc`.
+pub struct ContextPool {
+    compression: Mutex<Vec<CompressionContext>>,
+    decompression: Mutex<Vec<DecompressionContext>>,
+    max_idle: usize,
+}
+
+impl ContextPool {
+    /// Creates an empty `ContextPool` that keeps at most `max_idle` idle contexts of each kind.
+    /// Contexts put back beyond that are dropped.
+    pub fn new(max_idle: usize) -> ContextPool {
+        ContextPool {
+            compression: Mutex::new(Vec::new()),
+            decompression: Mutex::new(Vec::new()),
+            max_idle: max_idle,
+        }
+    }
+
+    /// Takes an idle compression context out of the pool, or creates one if there is none.
+    pub fn compression_context(&self) -> Lz4Result<CompressionContext> {
+        let idle: Option<CompressionContext> = self.compression.lock().unwrap().pop();
+        match idle {
+            Some(ctx) => Ok(ctx),
+            None => create_compression_context(),
+        }
+    }
+
+    /// Takes an idle decompression context out of the pool, or creates one if there is none.
+    pub fn decompression_context(&self) -> Lz4Result<DecompressionContext> {
+        let idle: Option<DecompressionContext> = self.decompression.lock().unwrap().pop();
+        match idle {
+            Some(ctx) => Ok(ctx),
+            None => create_decompression_context(),
+        }
+    }
+
+    /// Puts `ctx` back into the pool, unless it is in the middle of a frame or the pool is full.
+    pub fn put_compression_context(&self, ctx: CompressionContext) {
+        if ctx.in_frame() { return; }
+        let mut idle = self.compression.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(ctx);
+        }
+    }
+
+    /// Puts `ctx` back into the pool, unless it is in the middle of a frame or the pool is full.
+    /// Options an earlier user left behind are reset first.
+    pub fn put_decompression_context(&self, mut ctx: DecompressionContext) {
+        if ctx.in_frame() { return; }
+        ctx.reset_options();
+        let mut idle = self.decompression.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(ctx);
+        }
+    }
+
+    /// Returns how many idle compression and decompression contexts the pool holds.
+    pub fn idle(&self) -> (usize, usize) {
+        (self.compression.lock().unwrap().len(), self.decompression.lock().unwrap().len())
+    }
+
+    /// Creates a `Compressor` like `Compressor::new` with a context from the pool. Finish it with
+    /// `finish_compressor` to put the context back.
+    pub fn compressor<W: Writer>(&self,
+                                 dst: W,
+                                 prefs: Option<FramePreferences>,
+                                 buf_size: Option<usize>,
+                                 opts: Option<CompressOptions>)
+                                 -> Lz4Result<Compressor<W>> {
+        let ctx: CompressionContext = try!(self.compression_context());
+        Compressor::with_context(dst, ctx, prefs, buf_size, opts)
+    }
+
+    /// Finishes the frame `compressor` is writing like `Compressor::done`, and puts its context
+    /// back into the pool if that succeeded.
+    pub fn finish_compressor<W: Writer>(&self,
+                                        compressor: Compressor<W>) -> (W, Lz4Result<usize>) {
+        let (inner, ctx, end_res) = compressor.finish();
+        self.put_compression_context(ctx);
+        (inner, end_res)
+    }
+
+    /// Creates a `Decompressor` like `Decompressor::with_options` with a context from the pool.
+    /// Finish it with `finish_decompressor` to put the context back.
+    pub fn decompressor<R: Reader>(&self,
+                                   src: R,
+                                   buf_size: Option<usize>,
+                                   opts: Option<DecompressOptions>)
+                                   -> Lz4Result<Decompressor<R>> {
+        let ctx: DecompressionContext = try!(self.decompression_context());
+        Decompressor::with_context(src, ctx, buf_size, opts)
+    }
+
+    /// Unwraps `decompressor`, returning its inner `Reader`, and puts its context back into the
+    /// pool if the frame was read to its end.
+    pub fn finish_decompressor<R: Reader>(&self, decompressor: Decompressor<R>) -> R {
+        let (inner, ctx) = decompressor.into_parts();
+        self.put_decompression_context(ctx);
+        inner
+    }
+}
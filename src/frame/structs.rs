@@ -1,31 +1,37 @@
 //! This module contains various structs needed by both the compressor and decompressor modules
 //! as well as implementations of various necessary traits.
 
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::default::Default;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+#[cfg(not(feature = "pure-rust"))]
 use libc;
+#[cfg(not(feature = "pure-rust"))]
 use libc::c_char;
 
+#[cfg(not(feature = "pure-rust"))]
 use collections::slice;
 
+#[cfg(not(feature = "pure-rust"))]
 use std::mem;
+#[cfg(not(feature = "pure-rust"))]
 use std::str;
-use std::ptr;
-use std::ops::{Deref, DerefMut};
 
-use super::super::liblz4::frame::types::{
-    Context,
-    FrameErrorCode,
-};
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::FrameErrorCode;
 
-use super::super::liblz4::frame::{
-    LZ4F_VERSION,
-    LZ4F_getErrorName,
-    LZ4F_createCompressionContext,
-    LZ4F_freeCompressionContext,
-    LZ4F_createDecompressionContext,
-    LZ4F_freeDecompressionContext,
-};
+#[cfg(not(feature = "pure-rust"))]
+use lz4rs_sys::LZ4F_getErrorName;
 
-use super::is_error;
+use lz4rs_sys::{
+    FrameCompressOptions,
+    FrameDecompressOptions,
+};
 
 /// Convenient wrapper for returning Results
 pub type Lz4Result<T> = Result<T, Lz4Error>;
@@ -35,6 +41,28 @@ pub struct Lz4Error {
     pub desc: String, 
 }
 
+impl fmt::String for Lz4Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.desc)
+    }
+}
+
+/// Lets an `Lz4Error` be used wherever a `std::error::Error` is expected.
+#[cfg(feature = "std")]
+impl Error for Lz4Error {
+    fn description(&self) -> &str {
+        self.desc.as_slice()
+    }
+}
+
+/// Creates the error for a context handed to a `Compressor` or `Decompressor` in the middle of a
+/// frame, which it can't start another frame with.
+#[cfg(feature = "std")]
+pub fn context_in_frame() -> Lz4Error {
+    Lz4Error { desc: "context is in the middle of a frame".to_string() }
+}
+
+#[cfg(not(feature = "pure-rust"))]
 impl Lz4Error {
     /// Given an error code, retrieves the associated error string and wraps it in an `Lz4Error`
     /// struct.
@@ -44,82 +72,106 @@ impl Lz4Error {
     }
 }
 
-/// Retrieves string representation of given error code. 
-unsafe fn get_error_string(code: FrameErrorCode) -> String {
-    let emsg_ptr: *const c_char = LZ4F_getErrorName(code);
-    str_from_ptr(emsg_ptr)
+/// Options for compressing a frame: the `FrameCompressOptions` handed to lz4frame as they are,
+/// and the settings handled in Rust, which lz4frame never sees.
+pub struct CompressOptions {
+    /// Handed to lz4frame by `compress_update` and friends. `Compressor` and `CompressingReader`
+    /// clear `stable_src`, since the data they compress doesn't outlive a call.
+    pub frame: FrameCompressOptions,
+    /// Write the header checksum over the magic number too, like Kafka did before message format
+    /// v1.
+    pub legacy_header_checksum: bool,
 }
 
-/// Converts a pointer to a C string to a String buffer
-unsafe fn str_from_ptr(ptr: *const c_char) -> String {
-    let len: usize = (libc::strlen(ptr) as usize) + 1;
-    let char_slice: &[c_char] = slice::from_raw_buf(&ptr, len);
-    let byte_slice: &[u8] = mem::transmute(char_slice);
-    str::from_utf8(byte_slice).unwrap().to_string()
+impl Default for CompressOptions {
+    fn default() -> CompressOptions {
+        CompressOptions {
+            frame: Default::default(),
+            legacy_header_checksum: false,
+        }
+    }
 }
 
-/// Enum to indicate which type of compression context is being used inside an `Lz4FrameContxt`
-#[derive(Copy)]
-pub enum FrameContextType {
-    Compression,
-    Decompression,
+impl CompressOptions {
+    /// Creates `CompressOptions` handing `FrameCompressOptions::new(src_stable)` to lz4frame, whose
+    /// contract on the source buffers the caller takes on.
+    pub unsafe fn new(src_stable: bool) -> CompressOptions {
+        CompressOptions {
+            frame: FrameCompressOptions::new(src_stable),
+            legacy_header_checksum: false,
+        }
+    }
+
+    /// Sets whether frames are written with Kafka's legacy header checksum.
+    pub fn with_legacy_header_checksum(mut self, legacy: bool) -> CompressOptions {
+        self.legacy_header_checksum = legacy;
+        self
+    }
 }
 
-/// Wrapper around LZ4 contexts (compression and decompression)
-pub struct Lz4FrameContext {
-    pub ctx: Context,
-    ty: FrameContextType,
+/// Options for decompressing a frame: the `FrameDecompressOptions` handed to lz4frame as they are,
+/// and the settings handled in Rust, which lz4frame never sees.
+pub struct DecompressOptions {
+    /// Handed to lz4frame by `decompress`. `Decompressor` and `DecompressingWriter` clear
+    /// `stable_dst`, since the buffers they decompress into don't outlive a call.
+    pub frame: FrameDecompressOptions,
+    /// Expect the header checksum over the magic number too, like Kafka wrote it before message
+    /// format v1. The header is then checked in Rust and handed to lz4frame with a correct
+    /// checksum.
+    pub legacy_header_checksum: bool,
 }
 
-impl Lz4FrameContext {
-    /// Creates a new `Lz4FrameContext` based on which `FrameContextType` is given.
-    pub fn new(t: FrameContextType) -> Lz4Result<Lz4FrameContext> {
-        let mut ctx: Context = ptr::null_mut();
-        let err = match t {
-            FrameContextType::Compression => {
-                unsafe { LZ4F_createCompressionContext(&mut ctx, LZ4F_VERSION) }
-            },
-            FrameContextType::Decompression => {
-                unsafe { LZ4F_createDecompressionContext(&mut ctx, LZ4F_VERSION) }
-            },
-        };
-
-        if is_error(err) {
-            Err(Lz4Error::new(err))
-        } else {
-            Ok(Lz4FrameContext { ctx: ctx, ty: t })
+impl Default for DecompressOptions {
+    fn default() -> DecompressOptions {
+        DecompressOptions {
+            frame: Default::default(),
+            legacy_header_checksum: false,
         }
     }
 }
 
-/// Implementation of Deref for an Lz4FrameContext for a convienent way to
-/// access the underlying context.
-impl Deref for Lz4FrameContext {
-    type Target = Context;
+impl DecompressOptions {
+    /// Creates `DecompressOptions` handing `FrameDecompressOptions::new(dst_stable)` to lz4frame,
+    /// whose contract on the destination buffers the caller takes on.
+    pub unsafe fn new(dst_stable: bool) -> DecompressOptions {
+        DecompressOptions {
+            frame: FrameDecompressOptions::new(dst_stable),
+            legacy_header_checksum: false,
+        }
+    }
 
-    fn deref<'a>(&'a self) -> &'a Context {
-        &self.ctx
+    /// Sets whether frame headers are expected to carry Kafka's legacy header checksum.
+    pub fn with_legacy_header_checksum(mut self, legacy: bool) -> DecompressOptions {
+        self.legacy_header_checksum = legacy;
+        self
     }
 }
 
-/// Mutable implementation of Deref for an Lz4FrameContext for a convienent way to
-/// change the underlying context if needed.
-impl DerefMut for Lz4FrameContext {
-    fn deref_mut<'a>(&'a mut self) -> &'a mut Context {
-        &mut self.ctx
-    }
+/// How a frame written to a `DecompressingWriter` turned out, as reported by its `finish`.
+#[derive(Show, Clone, PartialEq)]
+pub enum FrameStatus {
+    /// The frame was complete, and so was its content checksum if it has one.
+    Complete,
+    /// The frame ended before its end mark, or before its content checksum.
+    Incomplete,
+    /// The frame's content checksum doesn't match the decompressed data.
+    ChecksumMismatch,
+    /// Decompressing the frame failed for another reason, which the description names.
+    Invalid(String),
 }
 
-/// Implements drop to ensure the underlying context is free'd properly.
-impl Drop for Lz4FrameContext {
-    fn drop(&mut self) {
-        match self.ty {
-            FrameContextType::Compression => {
-                unsafe { LZ4F_freeCompressionContext(self.ctx); }
-            },
-            FrameContextType::Decompression => {
-                unsafe { LZ4F_freeDecompressionContext(self.ctx); }
-            },
-        }
-    }
+/// Retrieves string representation of given error code. 
+#[cfg(not(feature = "pure-rust"))]
+unsafe fn get_error_string(code: FrameErrorCode) -> String {
+    let emsg_ptr: *const c_char = LZ4F_getErrorName(code);
+    str_from_ptr(emsg_ptr)
+}
+
+/// Converts a pointer to a C string to a String buffer
+#[cfg(not(feature = "pure-rust"))]
+unsafe fn str_from_ptr(ptr: *const c_char) -> String {
+    let len: usize = (libc::strlen(ptr) as usize) + 1;
+    let char_slice: &[c_char] = slice::from_raw_buf(&ptr, len);
+    let byte_slice: &[u8] = mem::transmute(char_slice);
+    str::from_utf8(byte_slice).unwrap().to_string()
 }
@@ -0,0 +1,377 @@
+//! This module reads and writes the block stream layout of Hadoop's `Lz4Codec`, which is what
+//! Hadoop, Hive and friends write into `.lz4` files instead of the LZ4 Framing Format.
+//! Hadoop's `BlockCompressorStream` splits its input into chunks, and writes each chunk as its
+//! uncompressed length followed by one or more compressed blocks, each prefixed by its compressed
+//! length. All lengths are 4 byte big endian integers, and there are no magic numbers or
+//! checksums.
+//! `HadoopLz4Reader` and `HadoopLz4Writer` are built on the functions in `core::block`, and need
+//! the `std` feature.
+
+use std::io::{IoResult, IoError, IoErrorKind};
+use std::io::fs::File;
+use std::cmp::min;
+use std::iter::repeat;
+use std::slice::bytes::copy_memory;
+
+use super::core::block;
+use super::frame::structs::{Lz4Error, Lz4Result};
+
+/// The buffer size `Lz4Codec` uses unless `io.compression.codec.lz4.buffersize` says otherwise.
+pub const DEFAULT_BUFFER_SIZE: usize = 256 * 1024;
+
+/// The largest uncompressed chunk `HadoopLz4Reader` accepts by default. Chunk lengths come from
+/// the input, so this keeps a corrupted length from making the reader allocate up to 4GB.
+pub const DEFAULT_MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Size of each length in the stream.
+const LENGTH_SIZE: usize = 4;
+
+/// The little endian magic numbers LZ4 frames and skippable frames start with.
+const FRAME_MAGIC_NUMBER: u32 = 0x184D2204;
+const SKIPPABLE_MAGIC_NUMBER: u32 = 0x184D2A50;
+
+/// How big the buffer `decompress_file` decompresses into is.
+const FILE_BUF_SIZE: usize = 64 * 1024;
+
+pub struct HadoopLz4Reader<R> {
+    inner: R,
+    compressed: Vec<u8>,
+    buffer: Vec<u8>,
+    buf_offset: usize,
+    buf_size: usize,
+    max_chunk_size: usize,
+    eof: bool,
+}
+
+impl<R: Reader> HadoopLz4Reader<R> {
+    /// Creates a new `HadoopLz4Reader` reading the block stream in `src`. Chunks whose declared
+    /// uncompressed length is larger than `max_chunk_size` are rejected; it defaults to
+    /// `DEFAULT_MAX_CHUNK_SIZE`.
+    pub fn new(src: R, max_chunk_size: Option<usize>) -> HadoopLz4Reader<R> {
+        HadoopLz4Reader {
+            inner: src,
+            compressed: Vec::new(),
+            buffer: Vec::new(),
+            buf_offset: 0,
+            buf_size: 0,
+            max_chunk_size: max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE),
+            eof: false,
+        }
+    }
+
+    /// Unwraps this `HadoopLz4Reader`, returning the underlying `Reader`.
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Reads and decompresses the next chunk into the internal buffer. Returns false if the
+    /// stream ended cleanly before the chunk.
+    fn read_chunk(&mut self) -> IoResult<bool> {
+        let mut length: [u8; LENGTH_SIZE] = [0; LENGTH_SIZE];
+        match try!(read_full(&mut self.inner, &mut length)) {
+            0 => { return Ok(false); },
+            LENGTH_SIZE => {},
+            _ => {
+                return Err(hadoop_error("lz4 decompress error", "chunk truncated".to_string()));
+            },
+        }
+        let size: usize = read_be_u32(&length) as usize;
+        if size > self.max_chunk_size {
+            return Err(hadoop_error("lz4 decompress error",
+                                    format!("chunk of {} bytes exceeds maximum of {}",
+                                            size, self.max_chunk_size)));
+        }
+        if size > self.buffer.len() {
+            let additional: usize = size - self.buffer.len();
+            self.buffer.extend(repeat(0u8).take(additional));
+        }
+
+        // a chunk may have been compressed into several blocks
+        let mut filled: usize = 0;
+        while filled < size {
+            if try!(read_full(&mut self.inner, &mut length)) != LENGTH_SIZE {
+                return Err(hadoop_error("lz4 decompress error", "chunk truncated".to_string()));
+            }
+            let compressed_size: usize = read_be_u32(&length) as usize;
+            if compressed_size > block::compress_bound(size - filled) {
+                return Err(hadoop_error("lz4 decompress error",
+                                        format!("block of {} bytes too large for its chunk",
+                                                compressed_size)));
+            }
+            if compressed_size > self.compressed.len() {
+                let additional: usize = compressed_size - self.compressed.len();
+                self.compressed.extend(repeat(0u8).take(additional));
+            }
+            let src: &mut [u8] = self.compressed.slice_to_mut(compressed_size);
+            if try!(read_full(&mut self.inner, src)) != compressed_size {
+                return Err(hadoop_error("lz4 decompress error", "chunk truncated".to_string()));
+            }
+
+            match block::decompress(self.compressed.slice_to(compressed_size),
+                                    self.buffer.slice_mut(filled, size)) {
+                Ok(len) => { filled += len; },
+                Err(lz4err) => { return Err(hadoop_error("lz4 decompress error", lz4err.desc)); },
+            }
+        }
+        self.buf_offset = 0;
+        self.buf_size = size;
+        Ok(true)
+    }
+}
+
+impl<R: Reader> Reader for HadoopLz4Reader<R> {
+    /// Reads decompressed data into `buf`, returning the number of bytes read, or an EndOfFile
+    /// IoError once the stream has been read completely.
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let mut dst_offset: usize = 0;
+        while dst_offset < buf.len() && !self.eof {
+            if self.buf_offset >= self.buf_size {
+                if !try!(self.read_chunk()) {
+                    self.eof = true;
+                    break;
+                }
+                continue;
+            }
+            let len: usize = min(buf.len() - dst_offset, self.buf_size - self.buf_offset);
+            copy_memory(buf.slice_mut(dst_offset, dst_offset + len),
+                        self.buffer.slice(self.buf_offset, self.buf_offset + len));
+            dst_offset += len;
+            self.buf_offset += len;
+        }
+        if dst_offset == 0 && self.eof && buf.len() > 0 {
+            return Err(IoError {
+                kind: IoErrorKind::EndOfFile,
+                desc: "No more to decompress",
+                detail: None,
+            });
+        }
+        Ok(dst_offset)
+    }
+}
+
+pub struct HadoopLz4Writer<W> {
+    inner: W,
+    chunk: Vec<u8>,
+    chunk_size: usize,
+    compressed: Vec<u8>,
+}
+
+impl<W: Writer> HadoopLz4Writer<W> {
+    /// Creates a new `HadoopLz4Writer` writing a block stream to `dst`. `buffer_size` is the
+    /// equivalent of `io.compression.codec.lz4.buffersize` and defaults to `DEFAULT_BUFFER_SIZE`;
+    /// like Hadoop, chunks are kept small enough that each compresses into a single block of at
+    /// most `buffer_size` bytes. Fails if `buffer_size` leaves no room for a chunk next to the
+    /// compression overhead, i.e. if it is 32 bytes or less.
+    pub fn new(dst: W, buffer_size: Option<usize>) -> Lz4Result<HadoopLz4Writer<W>> {
+        let buffer_size: usize = buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
+        let overhead: usize = buffer_size / 255 + 16;
+        if buffer_size <= 2 * overhead {
+            return Err(Lz4Error {
+                desc: format!("buffer size must be more than {} bytes", 2 * overhead),
+            });
+        }
+        let chunk_size: usize = buffer_size - overhead;
+        let bound: usize = block::compress_bound(chunk_size);
+        Ok(HadoopLz4Writer {
+            inner: dst,
+            chunk: Vec::with_capacity(chunk_size),
+            chunk_size: chunk_size,
+            compressed: repeat(0u8).take(bound).collect(),
+        })
+    }
+
+    /// Compresses whatever has been written since the last chunk and writes it to the inner
+    /// `Writer` as a chunk of its own.
+    fn write_chunk(&mut self) -> IoResult<()> {
+        if self.chunk.len() == 0 {
+            return Ok(());
+        }
+        let len: usize = match block::compress(self.chunk.as_slice(),
+                                               self.compressed.as_mut_slice(),
+                                               block::DEFAULT_ACCELERATION) {
+            Ok(len) => len,
+            Err(lz4err) => { return Err(hadoop_error("lz4 compress error", lz4err.desc)); },
+        };
+        try!(self.inner.write_be_u32(self.chunk.len() as u32));
+        try!(self.inner.write_be_u32(len as u32));
+        try!(self.inner.write(self.compressed.slice_to(len)));
+        self.chunk.clear();
+        Ok(())
+    }
+
+    /// Writes out the last chunk and returns the inner `Writer` for further use.
+    pub fn done(mut self) -> (W, IoResult<()>) {
+        let result: IoResult<()> = self.write_chunk();
+        (self.inner, result)
+    }
+}
+
+impl<W: Writer> Writer for HadoopLz4Writer<W> {
+    /// Buffers `buf` and writes out every chunk that fills up. Data that doesn't fill a chunk is
+    /// only written by `flush` or `done`.
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        let mut buf_offset: usize = 0;
+        while buf_offset < buf.len() {
+            let len: usize = min(self.chunk_size - self.chunk.len(), buf.len() - buf_offset);
+            self.chunk.push_all(buf.slice(buf_offset, buf_offset + len));
+            buf_offset += len;
+            if self.chunk.len() == self.chunk_size {
+                try!(self.write_chunk());
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out the data buffered so far as a chunk, which Hadoop reads like any other, and
+    /// flushes the inner `Writer`.
+    fn flush(&mut self) -> IoResult<()> {
+        try!(self.write_chunk());
+        self.inner.flush()
+    }
+}
+
+/// Checks whether `src`, the start of a file, looks like a Hadoop block stream rather than an
+/// LZ4 frame. `src` should hold at least 8 bytes: the lengths at the start of a block stream have
+/// to be consistent with each other, and can't be mistaken for a frame's magic number.
+pub fn is_hadoop_lz4(src: &[u8]) -> bool {
+    if src.len() < LENGTH_SIZE {
+        return false;
+    }
+    let magic: u32 = read_be_u32(src).swap_bytes();
+    if magic == FRAME_MAGIC_NUMBER || magic & 0xFFFFFFF0 == SKIPPABLE_MAGIC_NUMBER {
+        return false;
+    }
+    let size: usize = read_be_u32(src) as usize;
+    // Hadoop writes a lone empty chunk for empty input
+    if size == 0 {
+        return src.len() == LENGTH_SIZE;
+    }
+    if src.len() < 2 * LENGTH_SIZE {
+        return false;
+    }
+    let compressed_size: usize = read_be_u32(src.slice_from(LENGTH_SIZE)) as usize;
+    compressed_size > 0 && compressed_size <= block::compress_bound(size)
+}
+
+/// Decompresses the Hadoop block stream in the file at `src` into the file at `dst`.
+/// Returns the size of the decompressed file or an IoError if something failed during
+/// decompression.
+pub fn decompress_file(src: &Path, dst: &Path) -> IoResult<usize> {
+    let src_file = try!(File::open(src));
+    let mut dst_file = try!(File::create(dst));
+
+    let mut reader: HadoopLz4Reader<File> = HadoopLz4Reader::new(src_file, None);
+    let mut buf: Vec<u8> = repeat(0u8).take(FILE_BUF_SIZE).collect();
+    loop {
+        let len: usize = match reader.read(buf.as_mut_slice()) {
+            Ok(n) => n,
+            Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+            Err(e) => { return Err(e); },
+        };
+        try!(dst_file.write(buf.slice_to(len)));
+    }
+    let fstat = try!(dst_file.stat());
+    Ok(fstat.size as usize)
+}
+
+/// Reads from `src` until `buf` is full, returning how many bytes were read. This is less than
+/// `buf.len()` only if `src` reached EoF.
+fn read_full<R: Reader>(src: &mut R, buf: &mut [u8]) -> IoResult<usize> {
+    let mut filled: usize = 0;
+    while filled < buf.len() {
+        match src.read(buf.slice_from_mut(filled)) {
+            Ok(n) => { filled += n; },
+            Err(ref e) if e.kind == IoErrorKind::EndOfFile => { break; },
+            Err(e) => { return Err(e); },
+        }
+    }
+    Ok(filled)
+}
+
+/// Reads 4 bytes at the start of `src` as a big endian integer.
+fn read_be_u32(src: &[u8]) -> u32 {
+    ((src[0] as u32) << 24) | ((src[1] as u32) << 16) | ((src[2] as u32) << 8) | (src[3] as u32)
+}
+
+/// Wraps a description of a failed compression or decompression in an `IoError`.
+fn hadoop_error(desc: &'static str, detail: String) -> IoError {
+    IoError {
+        kind: IoErrorKind::OtherIoError,
+        desc: desc,
+        detail: Some(detail),
+    }
+}
+
+/// Simple tests that block streams are written and read like Hadoop does. See the crate docs for
+/// how they run under Miri.
+#[cfg(any(feature = "pure-rust", not(miri)))]
+mod hadoop_tests {
+    use std::io::MemReader;
+    use super::{HadoopLz4Reader, HadoopLz4Writer, is_hadoop_lz4, read_be_u32};
+
+    /// A chunk as Hadoop writes it: "0123456789" five times over, 50 bytes compressed into a 20
+    /// byte block.
+    const STREAM: &'static [u8] = b"\0\0\0\x32\0\0\0\x14\xaf0123456789\x0a\x00\x10\x5056789";
+
+    #[test]
+    fn reads_hadoop_stream() {
+        let mut reader: HadoopLz4Reader<MemReader> =
+            HadoopLz4Reader::new(MemReader::new(STREAM.to_vec()), None);
+        let data: Vec<u8> = reader.read_to_end().unwrap();
+        assert_eq!(b"01234567890123456789012345678901234567890123456789", data.as_slice());
+        assert!(is_hadoop_lz4(STREAM));
+        assert!(!is_hadoop_lz4(b"\x04\x22\x4d\x18\x64\x40\xa7\x00"));
+        assert!(!is_hadoop_lz4(b"\x50\x2a\x4d\x18\x10\x00\x00\x00"));
+    }
+
+    /// Tests that chunks longer than the maximum and truncated streams are rejected.
+    #[test]
+    fn rejects_bad_streams() {
+        let mut reader: HadoopLz4Reader<MemReader> =
+            HadoopLz4Reader::new(MemReader::new(STREAM.to_vec()), Some(49));
+        assert!(reader.read_to_end().is_err());
+
+        let truncated: Vec<u8> = STREAM.slice_to(STREAM.len() - 1).to_vec();
+        let mut reader: HadoopLz4Reader<MemReader> =
+            HadoopLz4Reader::new(MemReader::new(truncated), None);
+        assert!(reader.read_to_end().is_err());
+    }
+
+    /// Tests that data split over several chunks reads back the same.
+    #[test]
+    fn round_trip() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut writer: HadoopLz4Writer<Vec<u8>> = HadoopLz4Writer::new(Vec::new(), Some(40))
+                                                       .ok().unwrap();
+        writer.write(data).unwrap();
+        let (v, result) = writer.done();
+        result.unwrap();
+        assert!(is_hadoop_lz4(v.as_slice()));
+
+        let mut reader: HadoopLz4Reader<MemReader> = HadoopLz4Reader::new(MemReader::new(v), None);
+        assert_eq!(data, reader.read_to_end().unwrap().as_slice());
+    }
+
+    /// Tests that buffer sizes too small for a chunk are rejected, and that the smallest one
+    /// accepted still keeps every block within the buffer size.
+    #[test]
+    fn small_buffer_sizes() {
+        assert!(HadoopLz4Writer::new(Vec::new(), Some(0)).is_err());
+        assert!(HadoopLz4Writer::new(Vec::new(), Some(32)).is_err());
+
+        // incompressible, so every block comes out as large as it gets
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut writer: HadoopLz4Writer<Vec<u8>> = HadoopLz4Writer::new(Vec::new(), Some(33))
+                                                       .ok().unwrap();
+        writer.write(data.as_slice()).unwrap();
+        let (v, result) = writer.done();
+        result.unwrap();
+        let mut pos: usize = 0;
+        while pos < v.len() {
+            let len: usize = read_be_u32(v.slice_from(pos + 4)) as usize;
+            assert!(len <= 33);
+            pos += 8 + len;
+        }
+
+        let mut reader: HadoopLz4Reader<MemReader> = HadoopLz4Reader::new(MemReader::new(v), None);
+        assert_eq!(data, reader.read_to_end().unwrap());
+    }
+}
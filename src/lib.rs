@@ -1,17 +1,58 @@
 //! This library contains bindings to the various LZ4 formats. Currently, only the `frame`
-//! module is complete.
+//! module is complete. The `hadoop` module reads and writes the block streams of Hadoop's
+//! `Lz4Codec`.
+//!
+//! Without the default `std` feature the crate is `#![no_std]` and only needs `core` and
+//! `collections`: the block functions in `core::block`, the xxHash implementation and the
+//! slice-based frame functions in `frame::compress` and `frame::decompress` remain, while the
+//! `Compressor`, `Decompressor`, file helpers and the `hadoop` module go away. Together with
+//! `pure-rust`, no C library is needed either.
+//!
+//! The raw C bindings live in the `lz4rs-sys` crate, for code that needs them without this safe
+//! layer.
+//!
+//! The tests can run under Miri, which can neither call into the C library nor map files. Tests
+//! that need the C library are compiled out under Miri unless the `pure-rust` feature replaces it,
+//! and tests of the `mmap` helpers are always compiled out.
 
 #![allow(unstable)]
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate "core" as libcore;
 
 extern crate libc;
+extern crate lz4rs_sys;
+#[cfg_attr(not(feature = "std"), macro_use)]
 extern crate collections;
 
 pub use self::core::version;
 
-mod liblz4;
-
 pub mod frame;
 pub mod core;
+pub mod xxhash;
+#[cfg(feature = "std")]
+pub mod hadoop;
+
+/// Without the `std` feature, stands in for the parts of `std` the crate uses, so that modules
+/// (and `#[derive]`) can refer to them the same way either way.
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use libcore::{clone, cmp, default, fmt, i32, iter, marker, mem, ops, option, ptr, result};
+    pub use collections::{slice, str, string, vec};
+
+    /// What the `std` prelude provides, for modules to import explicitly.
+    pub mod prelude {
+        pub mod v1 {
+            pub use libcore::prelude::*;
+            pub use collections::slice::SliceExt;
+            pub use collections::str::StrExt;
+            pub use collections::string::{String, ToString};
+            pub use collections::vec::Vec;
+        }
+    }
+}
@@ -1,3 +0,0 @@
-
-pub mod core;
-pub mod frame;
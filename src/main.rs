@@ -4,10 +4,13 @@
 extern crate lz4rs;
 
 use std::os;
+use std::io::IoResult;
 use std::io::IoErrorKind::EndOfFile;
+use std::io::fs::File;
 
 use lz4rs::frame::decompress::decompress_file;
 use lz4rs::frame::compress::compress_file;
+use lz4rs::hadoop;
 
 const EXTENSION: &'static str = ".lz4";
 
@@ -18,7 +21,13 @@ fn main() {
         if arg.ends_with(EXTENSION) {
             let src: Path = Path::new(arg);
             let dst: Path = Path::new(arg.replace(EXTENSION, ""));
-            match decompress_file(&src, &dst, None) {
+            // files written by Hadoop's Lz4Codec hold a block stream rather than a frame
+            let result: IoResult<usize> = match is_hadoop_file(&src) {
+                Ok(true) => hadoop::decompress_file(&src, &dst),
+                Ok(false) => decompress_file(&src, &dst, None),
+                Err(e) => Err(e),
+            };
+            match result {
                 Ok(bytes) => {
                     println!("Decompressed {:?} into {} bytes", arg, bytes);
                 },
@@ -44,3 +53,18 @@ fn main() {
         }
     }
 }
+
+/// Checks whether the file at `path` starts like a Hadoop block stream.
+fn is_hadoop_file(path: &Path) -> IoResult<bool> {
+    let mut file: File = try!(File::open(path));
+    let mut start: [u8; 8] = [0; 8];
+    let mut len: usize = 0;
+    while len < start.len() {
+        match file.read(start.slice_from_mut(len)) {
+            Ok(n) => { len += n; },
+            Err(ref e) if e.kind == EndOfFile => { break; },
+            Err(e) => { return Err(e); },
+        }
+    }
+    Ok(hadoop::is_hadoop_lz4(start.slice_to(len)))
+}
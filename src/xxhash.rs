@@ -0,0 +1,166 @@
+//! A safe Rust implementation of the 32 bit variant of xxHash, which the LZ4 Framing Format uses
+//! for its header, block and content checksums.
+
+#![forbid(unsafe_code)]
+
+#[cfg(not(feature = "std"))]
+use std::prelude::v1::*;
+
+const PRIME32_1: u32 = 2654435761;
+const PRIME32_2: u32 = 2246822519;
+const PRIME32_3: u32 = 3266489917;
+const PRIME32_4: u32 = 668265263;
+const PRIME32_5: u32 = 374761393;
+
+/// Hashes `data` in one go, the same as `XXH32` in `xxhash.h`.
+pub fn xxh32(data: &[u8], seed: u32) -> u32 {
+    let mut state: Xxh32 = Xxh32::new(seed);
+    state.update(data);
+    state.digest()
+}
+
+/// Streaming state of an xxHash32 computation, for data that arrives in pieces.
+pub struct Xxh32 {
+    seed: u32,
+    v1: u32,
+    v2: u32,
+    v3: u32,
+    v4: u32,
+    total_len: u64,
+    mem: [u8; 16],
+    mem_size: usize,
+}
+
+impl Xxh32 {
+    /// Starts a new hash with the given `seed`.
+    pub fn new(seed: u32) -> Xxh32 {
+        Xxh32 {
+            seed: seed,
+            v1: add(add(seed, PRIME32_1), PRIME32_2),
+            v2: add(seed, PRIME32_2),
+            v3: seed,
+            v4: sub(seed, PRIME32_1),
+            total_len: 0,
+            mem: [0; 16],
+            mem_size: 0,
+        }
+    }
+
+    /// Adds `data` to the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        self.total_len += data.len() as u64;
+        let mut pos: usize = 0;
+
+        // complete the stripe left over from the last update first
+        if self.mem_size > 0 {
+            while self.mem_size < 16 && pos < data.len() {
+                self.mem[self.mem_size] = data[pos];
+                self.mem_size += 1;
+                pos += 1;
+            }
+            if self.mem_size < 16 { return; }
+            let mem: [u8; 16] = self.mem;
+            self.stripe(&mem, 0);
+            self.mem_size = 0;
+        }
+
+        while pos + 16 <= data.len() {
+            self.stripe(data, pos);
+            pos += 16;
+        }
+
+        while pos < data.len() {
+            self.mem[self.mem_size] = data[pos];
+            self.mem_size += 1;
+            pos += 1;
+        }
+    }
+
+    /// Returns the hash of all the data added so far. The state may still be updated afterwards.
+    pub fn digest(&self) -> u32 {
+        let mut h32: u32 = if self.total_len >= 16 {
+            add(add(rotl(self.v1, 1), rotl(self.v2, 7)), add(rotl(self.v3, 12), rotl(self.v4, 18)))
+        } else {
+            add(self.seed, PRIME32_5)
+        };
+        h32 = add(h32, self.total_len as u32);
+
+        let mut pos: usize = 0;
+        while pos + 4 <= self.mem_size {
+            h32 = add(h32, mul(read_u32(&self.mem, pos), PRIME32_3));
+            h32 = mul(rotl(h32, 17), PRIME32_4);
+            pos += 4;
+        }
+        while pos < self.mem_size {
+            h32 = add(h32, mul(self.mem[pos] as u32, PRIME32_5));
+            h32 = mul(rotl(h32, 11), PRIME32_1);
+            pos += 1;
+        }
+
+        h32 ^= h32 >> 15;
+        h32 = mul(h32, PRIME32_2);
+        h32 ^= h32 >> 13;
+        h32 = mul(h32, PRIME32_3);
+        h32 ^= h32 >> 16;
+        h32
+    }
+
+    /// Mixes the 16 bytes at `pos` in `data` into the accumulators.
+    fn stripe(&mut self, data: &[u8], pos: usize) {
+        self.v1 = round(self.v1, read_u32(data, pos));
+        self.v2 = round(self.v2, read_u32(data, pos + 4));
+        self.v3 = round(self.v3, read_u32(data, pos + 8));
+        self.v4 = round(self.v4, read_u32(data, pos + 12));
+    }
+}
+
+fn round(acc: u32, input: u32) -> u32 {
+    mul(rotl(add(acc, mul(input, PRIME32_2)), 13), PRIME32_1)
+}
+
+/// Reads 4 bytes at `pos` as a little endian integer.
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    (data[pos] as u32) | ((data[pos + 1] as u32) << 8) |
+        ((data[pos + 2] as u32) << 16) | ((data[pos + 3] as u32) << 24)
+}
+
+/// Addition modulo 2^32.
+fn add(a: u32, b: u32) -> u32 {
+    ((a as u64 + b as u64) & 0xFFFFFFFF) as u32
+}
+
+/// Subtraction modulo 2^32.
+fn sub(a: u32, b: u32) -> u32 {
+    ((a as u64 + (1u64 << 32) - b as u64) & 0xFFFFFFFF) as u32
+}
+
+/// Multiplication modulo 2^32.
+fn mul(a: u32, b: u32) -> u32 {
+    ((a as u64 * b as u64) & 0xFFFFFFFF) as u32
+}
+
+fn rotl(x: u32, r: usize) -> u32 {
+    (x << r) | (x >> (32 - r))
+}
+
+
+/// Checks against known hashes.
+mod xxhash_tests {
+    use super::{xxh32, Xxh32};
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(xxh32(b"", 0), 0x02CC5D05);
+        assert_eq!(xxh32(b"abc", 0), 0x32D153FF);
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let data: &[u8] = b"This is a test\nA what?\nA test\nA what?\nA test\nOh a test\n";
+        let mut state: Xxh32 = Xxh32::new(7);
+        for chunk in data.chunks(5) {
+            state.update(chunk);
+        }
+        assert_eq!(state.digest(), xxh32(data, 7));
+    }
+}